@@ -63,6 +63,7 @@ echo "Building Project"
 cd resources
 cargo build --release --target x86_64-unknown-linux-musl
 (cd target/x86_64-unknown-linux-musl/release && mkdir -p lambda && cp bootstrap lambda/)
+(cd target/x86_64-unknown-linux-musl/release && mkdir -p worker-lambda && cp worker worker-lambda/bootstrap)
 
 echo "NPM Install"
 npm install
@@ -1,10 +1,21 @@
-import { Construct, Stack, StackProps } from "@aws-cdk/core";
+import { Construct, Duration, Stack, StackProps } from "@aws-cdk/core";
 import { Architecture, Code, Function, Runtime } from "@aws-cdk/aws-lambda";
 import { Policy, PolicyStatement } from "@aws-cdk/aws-iam";
 import { LambdaRestApi } from '@aws-cdk/aws-apigateway';
 import { Table, AttributeType } from '@aws-cdk/aws-dynamodb';
+import { Queue } from "@aws-cdk/aws-sqs";
+import { SqsEventSource } from "@aws-cdk/aws-lambda-event-sources";
 import { RetentionDays } from "@aws-cdk/aws-logs";
 
+// Which channels each command may respond in, passed to the Lambda as CHANNEL_POLICIES.
+// See resources/src/channels.rs for the format.
+const channelPolicyProfiles = {
+  // Only respond in #devil-bot-test while developing.
+  test: { default: { allowlist: ["C0351GJ62Q0"] } },
+  // Respond everywhere DevilBot has been invited to.
+  everywhere: { default: "allow_all" },
+};
+
 export class DevilBotRustCdkStack extends Stack {
   constructor(scope: Construct, id: string, props?: StackProps) {
     super(scope, id, props);
@@ -17,6 +28,78 @@ export class DevilBotRustCdkStack extends Stack {
       }
     });
 
+    // Dynamo DB Table holding everyone's (and everything's) karma total.
+    const karmaTable = new Table(this, "karma-table", {
+      partitionKey: {
+        name: "target",
+        type: AttributeType.STRING
+      }
+    });
+
+    // Dynamo DB Table recording every karma change by who made it, used to rate limit givers.
+    const karmaHistoryTable = new Table(this, "karma-history-table", {
+      partitionKey: {
+        name: "giver",
+        type: AttributeType.STRING
+      },
+      sortKey: {
+        name: "given_at",
+        type: AttributeType.STRING
+      }
+    });
+
+    // Dynamo DB Table remembering which Slack events were already handled so retries are skipped,
+    // and which commands already ran for them so a retried job doesn't run them twice.
+    const eventsTable = new Table(this, "events-table", {
+      partitionKey: {
+        name: "event_id",
+        type: AttributeType.STRING
+      },
+      timeToLiveAttribute: "expires_at"
+    });
+
+    // Dynamo DB Table of upcoming club meetings and events, shown in App Home. Officers add items by hand,
+    // see resources/src/commands/app_home.rs for the attributes.
+    const clubEventsTable = new Table(this, "club-events-table", {
+      partitionKey: {
+        name: "event_id",
+        type: AttributeType.STRING
+      }
+    });
+
+    // Jobs the worker gave up on after a few attempts end up here, for two weeks.
+    const jobsDeadLetterQueue = new Queue(this, "jobs-dead-letter-queue", {
+      retentionPeriod: Duration.days(14)
+    });
+
+    // Slack events waiting to be processed by the worker. The endpoint only
+    // acknowledges them so it can answer Slack within 3 seconds.
+    const jobsQueue = new Queue(this, "jobs-queue", {
+      // Must be at least the worker's timeout, or a job could run twice at once.
+      visibilityTimeout: Duration.seconds(60),
+      deadLetterQueue: {
+        queue: jobsDeadLetterQueue,
+        maxReceiveCount: 3
+      }
+    });
+
+    // Environment shared by both Lambda functions.
+    // Fill in your personal app's webhook URLs below when testing (remove them when creating a PR)
+    const environment = {
+      RUST_BACKTRACE: "1",
+      SLACK_API_BOT_TOKEN: "",
+      SLACK_SIGNING_SECRET: "", // Found under "App Credentials" on your Slack app's "Basic Information" page
+      DEVIL_BOT_TEST_CHANNEL_URL: "",
+      DEVIL_BOT_DEV_CHANNEL_URL: "",
+      CHANNEL_POLICIES: JSON.stringify(channelPolicyProfiles.test),
+      BUNS_TABLE_NAME: bunsTable.tableName,
+      KARMA_TABLE_NAME: karmaTable.tableName,
+      KARMA_HISTORY_TABLE_NAME: karmaHistoryTable.tableName,
+      EVENTS_TABLE_NAME: eventsTable.tableName,
+      CLUB_EVENTS_TABLE_NAME: clubEventsTable.tableName,
+      JOBS_QUEUE_URL: jobsQueue.queueUrl
+    };
+
     // Lambda function that wraps the Rust binary.
     const rustSlackLambda = new Function(this, "rust-slack-lambda", {
       description:
@@ -27,33 +110,95 @@ export class DevilBotRustCdkStack extends Stack {
       runtime: Runtime.PROVIDED_AL2,
       architecture: Architecture.X86_64,
       handler: "not.required",
-      environment: { // Fill in your personal app's webhook URLs below when testing (remove them when creating a PR)
-        RUST_BACKTRACE: "1",
-        SLACK_API_BOT_TOKEN: "",
-        DEVIL_BOT_TEST_CHANNEL_URL: "",
-        DEVIL_BOT_DEV_CHANNEL_URL: "",
-        BUNS_TABLE_NAME: bunsTable.tableName
-      },
+      environment,
       logRetention: RetentionDays.ONE_DAY, // There will be a lot of event logs, this will make sure to cut down on costs
     });
 
+    // Lambda function that runs the commands for the events queued by rust-slack-lambda.
+    const rustWorkerLambda = new Function(this, "rust-worker-lambda", {
+      description: "Runs the commands for Slack events queued by the Slack bot endpoint.",
+      code: Code.fromAsset(
+        "resources/target/x86_64-unknown-linux-musl/release/worker-lambda"
+      ),
+      runtime: Runtime.PROVIDED_AL2,
+      architecture: Architecture.X86_64,
+      handler: "not.required",
+      timeout: Duration.seconds(60),
+      environment,
+      logRetention: RetentionDays.ONE_DAY,
+    });
+    // One job per invocation, so a failure only retries that job.
+    rustWorkerLambda.addEventSource(new SqsEventSource(jobsQueue, { batchSize: 1 }));
+    jobsQueue.grantSendMessages(rustSlackLambda);
+
+    // The endpoint and the worker share the same table access.
+    const rustLambdas = [rustSlackLambda, rustWorkerLambda];
+
     // Add Dynamo read/write access to the buns table.
-    rustSlackLambda.role?.attachInlinePolicy(
-      new Policy(this, "read-write-buns-table-policy", {
-        statements: [
-          new PolicyStatement({
-            actions: [
-              "dynamodb:DescribeTable",
-              "dynamodb:Query",
-              "dynamodb:Scan",
-              "dynamodb:PutItem",
-              "dynamodb:UpdateItem"
-            ],
-            resources: [bunsTable.tableArn],
-          })
-        ]
-      })
-    );
+    const bunsTablePolicy = new Policy(this, "read-write-buns-table-policy", {
+      statements: [
+        new PolicyStatement({
+          actions: [
+            "dynamodb:DescribeTable",
+            "dynamodb:GetItem",
+            "dynamodb:Query",
+            "dynamodb:Scan",
+            "dynamodb:PutItem",
+            "dynamodb:UpdateItem"
+          ],
+          resources: [bunsTable.tableArn],
+        })
+      ]
+    });
+
+    // Add Dynamo read/write access to the karma tables.
+    const karmaTablesPolicy = new Policy(this, "read-write-karma-tables-policy", {
+      statements: [
+        new PolicyStatement({
+          actions: [
+            "dynamodb:GetItem",
+            "dynamodb:Query",
+            "dynamodb:PutItem",
+            "dynamodb:UpdateItem"
+          ],
+          resources: [karmaTable.tableArn, karmaHistoryTable.tableArn],
+        })
+      ]
+    });
+
+    // Add Dynamo write access to the events table. Events are deleted again
+    // when they can't be queued, so Slack's retry isn't skipped, and so are
+    // commands that failed, so the job's retry runs them again.
+    const eventsTablePolicy = new Policy(this, "write-events-table-policy", {
+      statements: [
+        new PolicyStatement({
+          actions: [
+            "dynamodb:PutItem",
+            "dynamodb:DeleteItem"
+          ],
+          resources: [eventsTable.tableArn],
+        })
+      ]
+    });
+
+    // Add Dynamo read access to the club events table.
+    const clubEventsTablePolicy = new Policy(this, "read-club-events-table-policy", {
+      statements: [
+        new PolicyStatement({
+          actions: [
+            "dynamodb:Scan"
+          ],
+          resources: [clubEventsTable.tableArn],
+        })
+      ]
+    });
+
+    for (const lambda of rustLambdas) {
+      lambda.role?.attachInlinePolicy(bunsTablePolicy);
+      lambda.role?.attachInlinePolicy(karmaTablesPolicy);
+      lambda.role?.attachInlinePolicy(eventsTablePolicy);
+      lambda.role?.attachInlinePolicy(clubEventsTablePolicy);
+    }
 
     // Defines an API Gateway REST API resource backed by the "rust-slack-lambda" function.
     new LambdaRestApi(this, 'RustSlackEndpoint', {
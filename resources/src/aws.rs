@@ -1 +1,2 @@
 pub mod dynamo;
+pub mod sqs;
@@ -1,39 +1,278 @@
-use aws_sdk_dynamodb::{model::AttributeValue, Client, Error};
-
-pub async fn increment_item(
-    table_name: &str,
-    key: &str,
-    user_id: &str,
-    item_name: &str,
-) -> Result<(), Error> {
+use crate::store::{Item, Key, Query, Store, Value};
+use async_trait::async_trait;
+use aws_sdk_dynamodb::model::{AttributeValue, ReturnValue};
+use aws_sdk_dynamodb::{Client, Endpoint, Error};
+use lambda_http::http::Uri;
+use lambda_http::Error as BoxError;
+use std::collections::HashMap;
+
+// Builds the DynamoDB client. Do this once at cold start and share it, so
+// credentials are loaded and connections opened only once per Lambda
+// instance. `endpoint` overrides the AWS endpoint, e.g. to point at
+// DynamoDB Local (http://localhost:8000).
+pub async fn new_client(endpoint: Option<&Uri>) -> Client {
     let shared_config = aws_config::load_from_env().await;
-    let client = Client::new(&shared_config);
-
-    // Increment the value of item_name attribute if it exists.
-    let increment_if_exists_request = client
-        .update_item()
-        .table_name(table_name)
-        .key(key, AttributeValue::S(user_id.to_string()))
-        .update_expression(format!("set {item_name} = {item_name} + :value"))
-        .expression_attribute_values(":value", AttributeValue::N("1".to_string()))
-        .condition_expression(format!("attribute_exists({item_name})"));
-
-    let increment_if_exists_response = increment_if_exists_request
-        .send()
-        .await
-        .map_err(|e| Error::from(e));
-
-    // Create a new item with value 1 for the item_name attribute if it does not yet exist.
-    if let Err(Error::ConditionalCheckFailedException(_err)) = increment_if_exists_response {
-        let create_if_dne_request = client
+    let mut dynamo_config = aws_sdk_dynamodb::config::Builder::from(&shared_config);
+    if let Some(endpoint) = endpoint {
+        dynamo_config = dynamo_config.endpoint_resolver(Endpoint::immutable(endpoint.clone()));
+    }
+    Client::from_conf(dynamo_config.build())
+}
+
+// The Store backed by DynamoDB. Table names are passed per call, so one
+// DynamoStore serves every table.
+pub struct DynamoStore {
+    client: Client,
+}
+
+impl DynamoStore {
+    pub fn new(client: Client) -> Self {
+        DynamoStore { client }
+    }
+}
+
+fn to_attribute(value: &Value) -> AttributeValue {
+    match value {
+        Value::S(value) => AttributeValue::S(value.clone()),
+        Value::N(value) => AttributeValue::N(value.to_string()),
+    }
+}
+
+fn to_attributes(item: &Item) -> HashMap<String, AttributeValue> {
+    item.iter()
+        .map(|(name, value)| (name.clone(), to_attribute(value)))
+        .collect()
+}
+
+fn from_attributes(attributes: &HashMap<String, AttributeValue>) -> Result<Item, BoxError> {
+    attributes
+        .iter()
+        .map(|(name, attribute)| {
+            let value = match attribute {
+                AttributeValue::S(value) => Value::S(value.clone()),
+                AttributeValue::N(number) => Value::N(number.parse()?),
+                other => return Err(format!("{} has unsupported type {:?}", name, other).into()),
+            };
+            Ok((name.clone(), value))
+        })
+        .collect()
+}
+
+#[async_trait]
+impl Store for DynamoStore {
+    async fn get(&self, table: &str, key: &Key) -> Result<Option<Item>, BoxError> {
+        let response = self
+            .client
+            .get_item()
+            .table_name(table)
+            .set_key(Some(to_attributes(&key.to_item())))
+            .send()
+            .await?;
+        response.item().map(from_attributes).transpose()
+    }
+
+    async fn put(&self, table: &str, key: &Key, attributes: Item) -> Result<(), BoxError> {
+        let mut item = attributes;
+        item.extend(key.to_item());
+        self.client
+            .put_item()
+            .table_name(table)
+            .set_item(Some(to_attributes(&item)))
+            .send()
+            .await?;
+        Ok(())
+    }
+
+    // Uses a condition on the partition key, so two Lambda instances
+    // racing to write the same item can't both succeed.
+    async fn put_if_absent(
+        &self,
+        table: &str,
+        key: &Key,
+        attributes: Item,
+    ) -> Result<bool, BoxError> {
+        let mut item = attributes;
+        item.extend(key.to_item());
+        let put_if_absent_response = self
+            .client
+            .put_item()
+            .table_name(table)
+            .set_item(Some(to_attributes(&item)))
+            .condition_expression("attribute_not_exists(#key)")
+            .expression_attribute_names("#key", &key.partition.0)
+            .send()
+            .await
+            .map_err(Error::from);
+
+        match put_if_absent_response {
+            Ok(_) => Ok(true),
+            Err(Error::ConditionalCheckFailedException(_err)) => Ok(false),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    // A single atomic UpdateItem call. DynamoDB's ADD treats a missing item
+    // or attribute as zero, so the first increment creates it and concurrent
+    // increments can't overwrite each other.
+    async fn increment(
+        &self,
+        table: &str,
+        key: &Key,
+        attribute: &str,
+        delta: i64,
+    ) -> Result<i64, BoxError> {
+        let response = self
+            .client
             .update_item()
-            .table_name(table_name)
-            .key(key, AttributeValue::S(user_id.to_string()))
-            .update_expression(format!("set {item_name} = :value"))
-            .expression_attribute_values(":value", AttributeValue::N("1".to_string()));
+            .table_name(table)
+            .set_key(Some(to_attributes(&key.to_item())))
+            .update_expression("ADD #item :delta")
+            .expression_attribute_names("#item", attribute)
+            .expression_attribute_values(":delta", AttributeValue::N(delta.to_string()))
+            .return_values(ReturnValue::UpdatedNew)
+            .send()
+            .await?;
+
+        // ADD always returns the attribute it updated when asked for UPDATED_NEW.
+        let new_value: i64 = response
+            .attributes()
+            .and_then(|attributes| attributes.get(attribute))
+            .and_then(|value| value.as_n().ok())
+            .and_then(|number| number.parse().ok())
+            .unwrap_or_default();
+        Ok(new_value)
+    }
+
+    // Follows LastEvaluatedKey until every page has been read.
+    async fn query(&self, table: &str, query: &Query) -> Result<Vec<Item>, BoxError> {
+        let mut items: Vec<Item> = Vec::new();
+        let mut start_key: Option<HashMap<String, AttributeValue>> = None;
+        loop {
+            let (page, last_key) = match query {
+                Query::Scan => {
+                    let response = self
+                        .client
+                        .scan()
+                        .table_name(table)
+                        .set_exclusive_start_key(start_key)
+                        .send()
+                        .await?;
+                    (
+                        response.items().unwrap_or_default().to_vec(),
+                        response.last_evaluated_key().cloned(),
+                    )
+                }
+                Query::Partition {
+                    name,
+                    value,
+                    sort_from,
+                } => {
+                    let mut request = self
+                        .client
+                        .query()
+                        .table_name(table)
+                        .expression_attribute_names("#partition", name)
+                        .expression_attribute_values(":partition", to_attribute(value))
+                        .set_exclusive_start_key(start_key);
+                    request = match sort_from {
+                        Some((sort_key, from)) => request
+                            .key_condition_expression("#partition = :partition AND #sort >= :sort")
+                            .expression_attribute_names("#sort", sort_key)
+                            .expression_attribute_values(":sort", to_attribute(from)),
+                        None => request.key_condition_expression("#partition = :partition"),
+                    };
+                    let response = request.send().await?;
+                    (
+                        response.items().unwrap_or_default().to_vec(),
+                        response.last_evaluated_key().cloned(),
+                    )
+                }
+            };
+            for attributes in &page {
+                items.push(from_attributes(attributes)?);
+            }
+            match last_key {
+                Some(last_key) if !last_key.is_empty() => start_key = Some(last_key),
+                _ => return Ok(items),
+            }
+        }
+    }
+
+    async fn delete(&self, table: &str, key: &Key) -> Result<(), BoxError> {
+        self.client
+            .delete_item()
+            .table_name(table)
+            .set_key(Some(to_attributes(&key.to_item())))
+            .send()
+            .await?;
+        Ok(())
+    }
+}
+
+// These run against DynamoDB Local instead of AWS, e.g.
+//   docker run -p 8000:8000 amazon/dynamodb-local
+//   DYNAMODB_ENDPOINT=http://localhost:8000 AWS_REGION=us-east-1 \
+//   AWS_ACCESS_KEY_ID=local AWS_SECRET_ACCESS_KEY=local cargo test -- --ignored
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use aws_sdk_dynamodb::model::{
+        AttributeDefinition, BillingMode, KeySchemaElement, KeyType, ScalarAttributeType,
+    };
 
-        create_if_dne_request.send().await?;
+    async fn local_table(key: &str) -> (DynamoStore, String) {
+        let endpoint: Uri = std::env::var("DYNAMODB_ENDPOINT")
+            .expect("DYNAMODB_ENDPOINT must point at DynamoDB Local")
+            .parse()
+            .unwrap();
+        let client = new_client(Some(&endpoint)).await;
+        let table_name = format!("test-{}", crate::unix_now());
+        client
+            .create_table()
+            .table_name(&table_name)
+            .key_schema(
+                KeySchemaElement::builder()
+                    .attribute_name(key)
+                    .key_type(KeyType::Hash)
+                    .build(),
+            )
+            .attribute_definitions(
+                AttributeDefinition::builder()
+                    .attribute_name(key)
+                    .attribute_type(ScalarAttributeType::S)
+                    .build(),
+            )
+            .billing_mode(BillingMode::PayPerRequest)
+            .send()
+            .await
+            .unwrap();
+        (DynamoStore::new(client), table_name)
     }
 
-    Ok(())
+    #[tokio::test]
+    #[ignore = "needs DynamoDB Local"]
+    async fn puts_item_only_once() {
+        let (store, table_name) = local_table("event_id").await;
+        let key = Key::new("event_id", "Ev1");
+
+        let first = store.put_if_absent(&table_name, &key, Item::new());
+        assert!(first.await.unwrap());
+        let second = store.put_if_absent(&table_name, &key, Item::new());
+        assert!(!second.await.unwrap());
+    }
+
+    #[tokio::test]
+    #[ignore = "needs DynamoDB Local"]
+    async fn increments_new_and_existing_items() {
+        let (store, table_name) = local_table("user_id").await;
+        let key = Key::new("user_id", "U123");
+
+        let increment = |delta| store.increment(&table_name, &key, "buns", delta);
+        assert_eq!(increment(1).await.unwrap(), 1);
+        assert_eq!(increment(1).await.unwrap(), 2);
+        assert_eq!(increment(-5).await.unwrap(), -3);
+
+        let item = store.get(&table_name, &key).await.unwrap().unwrap();
+        assert_eq!(item.get("buns"), Some(&Value::N(-3)));
+    }
 }
@@ -0,0 +1,36 @@
+use crate::queue::{Job, Queue};
+use async_trait::async_trait;
+use aws_sdk_sqs::Client;
+use lambda_http::Error;
+
+// Builds the SQS client. Like the DynamoDB one, build it once per process.
+pub async fn new_client() -> Client {
+    let shared_config = aws_config::load_from_env().await;
+    Client::new(&shared_config)
+}
+
+// Sends jobs to an SQS queue, which triggers the worker Lambda. Retries and
+// the dead-letter queue are configured on the queue itself, see the CDK stack.
+pub struct SqsQueue {
+    client: Client,
+    queue_url: String,
+}
+
+impl SqsQueue {
+    pub fn new(client: Client, queue_url: String) -> Self {
+        SqsQueue { client, queue_url }
+    }
+}
+
+#[async_trait]
+impl Queue for SqsQueue {
+    async fn enqueue(&self, job: &Job) -> Result<(), Error> {
+        self.client
+            .send_message()
+            .queue_url(&self.queue_url)
+            .message_body(serde_json::to_string(job)?)
+            .send()
+            .await?;
+        Ok(())
+    }
+}
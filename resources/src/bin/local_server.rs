@@ -0,0 +1,104 @@
+// Serves DevilBot's handler, and runs its worker, over plain HTTP so it can
+// be developed without deploying to AWS. Point a tunnel (e.g. `ngrok http 3000`) at it to receive
+// real Slack events, or curl fixture payloads at it directly. See the
+// "Running locally" section of the README.
+//
+// It reads the same environment variables as the Lambda. Data is kept in
+// memory unless DYNAMODB_ENDPOINT points at a DynamoDB, e.g. DynamoDB Local.
+
+use devil_bot_rust::aws::dynamo::{self, DynamoStore};
+use devil_bot_rust::config::Config;
+use devil_bot_rust::store::memory::MemoryStore;
+use devil_bot_rust::store::Store;
+use devil_bot_rust::{queue, worker, AppState};
+use hyper::service::{make_service_fn, service_fn};
+use hyper::{Server, StatusCode};
+use lambda_http::{Body, Error, Request};
+use log::LevelFilter;
+use simple_logger::SimpleLogger;
+use std::convert::Infallible;
+use std::net::SocketAddr;
+use std::sync::Arc;
+
+const PORT: &str = "PORT";
+const DEFAULT_PORT: u16 = 3000;
+
+#[tokio::main]
+async fn main() -> Result<(), Error> {
+    SimpleLogger::new()
+        .with_level(LevelFilter::Info)
+        .init()
+        .unwrap();
+
+    let config = Config::from_env().map_err(|err| {
+        log::error!("{}", err);
+        err
+    })?;
+    let port: u16 = match std::env::var(PORT) {
+        Ok(port) => port.parse()?,
+        Err(_) => DEFAULT_PORT,
+    };
+
+    let store: Arc<dyn Store> = match &config.dynamodb_endpoint {
+        Some(endpoint) => Arc::new(DynamoStore::new(dynamo::new_client(Some(endpoint)).await)),
+        None => {
+            log::info!("No DYNAMODB_ENDPOINT set, keeping data in memory");
+            Arc::new(MemoryStore::default())
+        }
+    };
+    // Events are handed to a worker task in this process instead of SQS.
+    let (queue, jobs) = queue::local::channel();
+    let state = Arc::new(AppState::new(config, store, Arc::new(queue)));
+    tokio::spawn(worker::run_local(jobs, state.clone()));
+
+    let make_service = make_service_fn(move |_| {
+        let state = state.clone();
+        async move {
+            Ok::<_, Infallible>(service_fn(move |request| {
+                let state = state.clone();
+                async move { Ok::<_, Infallible>(serve(request, &state).await) }
+            }))
+        }
+    });
+    let addr = SocketAddr::from(([127, 0, 0, 1], port));
+    let server = Server::bind(&addr).serve(make_service);
+    log::info!("Listening on http://{}", addr);
+    server.await?;
+    Ok(())
+}
+
+// Translates between hyper and the lambda_http types the handler uses. Both
+// build on the same http crate, so only the bodies need converting.
+async fn serve(
+    request: hyper::Request<hyper::Body>,
+    state: &AppState,
+) -> hyper::Response<hyper::Body> {
+    let (parts, body) = request.into_parts();
+    let body = match hyper::body::to_bytes(body).await {
+        Ok(body) => body,
+        Err(err) => return error_response(StatusCode::BAD_REQUEST, err.to_string()),
+    };
+    let request = Request::from_parts(parts, Body::from(body.to_vec()));
+
+    match devil_bot_rust::handler(request, state).await {
+        Ok(response) => {
+            let (parts, body) = response.into_parts();
+            let body = match body {
+                Body::Empty => hyper::Body::empty(),
+                Body::Text(text) => hyper::Body::from(text),
+                Body::Binary(bytes) => hyper::Body::from(bytes),
+            };
+            hyper::Response::from_parts(parts, body)
+        }
+        Err(err) => {
+            log::error!("Handler failed: {}", err);
+            error_response(StatusCode::INTERNAL_SERVER_ERROR, err.to_string())
+        }
+    }
+}
+
+fn error_response(status: StatusCode, message: String) -> hyper::Response<hyper::Body> {
+    let mut response = hyper::Response::new(hyper::Body::from(message));
+    *response.status_mut() = status;
+    response
+}
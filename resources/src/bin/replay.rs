@@ -0,0 +1,68 @@
+// Replays Slack request bodies through DevilBot's handler without touching
+// Slack or AWS, and prints every Web API call and DB write it made.
+//
+//   cargo run --features testing --bin replay -- fixtures/events/02_ping.json
+//   cargo run --features testing --bin replay -- fixtures/events
+//
+// Directories are replayed file by file in name order. Every file shares one
+// in-memory store, so a later file sees what an earlier one wrote, and a file
+// that fails doesn't stop the ones after it. Each body
+// is signed just before it is sent, so fixtures never go stale. Environment
+// variables the Lambda reads override the defaults in testing::config, e.g.
+// CHANNEL_POLICIES to try a channel policy.
+
+use devil_bot_rust::testing::Harness;
+use lambda_http::{Body, Error};
+use std::path::{Path, PathBuf};
+
+#[tokio::main]
+async fn main() -> Result<(), Error> {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.is_empty() {
+        eprintln!("usage: replay <event.json | directory>...");
+        std::process::exit(2);
+    }
+    let mut files: Vec<PathBuf> = Vec::new();
+    for arg in &args {
+        files.extend(fixture_files(Path::new(arg))?);
+    }
+
+    let harness = Harness::with_vars(|name| std::env::var(name).ok()).await?;
+
+    for file in files {
+        println!("== {}", file.display());
+        let body: Vec<u8> = std::fs::read(&file)?;
+        match harness.send(body).await {
+            Ok(response) => match response.body() {
+                Body::Empty => println!("<- {}", response.status()),
+                Body::Text(text) => println!("<- {} {}", response.status(), text),
+                Body::Binary(bytes) => println!(
+                    "<- {} {}",
+                    response.status(),
+                    String::from_utf8_lossy(bytes)
+                ),
+            },
+            Err(err) => println!("<- failed: {}", err),
+        }
+        for call in harness.slack.take_calls() {
+            println!("slack {}", call);
+        }
+        for write in harness.store.take_writes() {
+            println!("store {}", write);
+        }
+        println!();
+    }
+    Ok(())
+}
+
+fn fixture_files(path: &Path) -> Result<Vec<PathBuf>, Error> {
+    if !path.is_dir() {
+        return Ok(vec![path.to_path_buf()]);
+    }
+    let mut files: Vec<PathBuf> = std::fs::read_dir(path)?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<Result<_, _>>()?;
+    files.retain(|file| file.extension().is_some_and(|ext| ext == "json"));
+    files.sort();
+    Ok(files)
+}
@@ -0,0 +1,58 @@
+// The Lambda behind the jobs queue. SQS invokes it with the jobs the
+// `bootstrap` Lambda queued up, one at a time (see the CDK stack). Returning
+// an error leaves the job on the queue to be retried; after a few failed
+// attempts SQS moves it to the dead-letter queue. worker::process only fails
+// for errors a retry might fix, anything else is logged and acknowledged.
+
+use aws_lambda_events::event::sqs::SqsEvent;
+use devil_bot_rust::aws::dynamo::{self, DynamoStore};
+use devil_bot_rust::aws::sqs::{self, SqsQueue};
+use devil_bot_rust::config::Config;
+use devil_bot_rust::queue::Job;
+use devil_bot_rust::{worker, AppState};
+use lambda_runtime::{service_fn, Error, LambdaEvent};
+use log::LevelFilter;
+use simple_logger::SimpleLogger;
+use std::sync::Arc;
+
+#[tokio::main]
+async fn main() -> Result<(), Error> {
+    SimpleLogger::new()
+        .with_utc_timestamps()
+        .with_level(LevelFilter::Info)
+        .init()
+        .unwrap();
+
+    let config = Config::from_env().map_err(|err| {
+        log::error!("{}", err);
+        err
+    })?;
+    let queue_url: String = config.jobs_queue_url.clone().ok_or_else(|| {
+        log::error!("JOBS_QUEUE_URL is required to run on Lambda");
+        "JOBS_QUEUE_URL is not set"
+    })?;
+
+    // The worker never queues jobs itself, but shares AppState with the
+    // handler, which expects a queue.
+    let client = dynamo::new_client(config.dynamodb_endpoint.as_ref()).await;
+    let store = Arc::new(DynamoStore::new(client));
+    let queue = Arc::new(SqsQueue::new(sqs::new_client().await, queue_url));
+    let state = Arc::new(AppState::new(config, store, queue));
+
+    let func = service_fn(move |event: LambdaEvent<SqsEvent>| {
+        let state = state.clone();
+        async move { handle(event.payload, &state).await }
+    });
+    lambda_runtime::run(func).await?;
+    Ok(())
+}
+
+async fn handle(event: SqsEvent, state: &AppState) -> Result<(), Error> {
+    for record in event.records {
+        let body: String = record.body.unwrap_or_default();
+        let job: Job = serde_json::from_str(&body)?;
+        log::info!("Processing {:?} job {:?}", record.message_id, job);
+        worker::process(&job, state).await?;
+    }
+    Ok(())
+}
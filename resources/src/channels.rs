@@ -0,0 +1,119 @@
+use serde_derive::Deserialize;
+use std::collections::HashMap;
+
+// Decides which channels a command is allowed to respond in. Policies are
+// read from the CHANNEL_POLICIES environment variable as JSON, e.g.
+//
+//   {
+//     "default": { "allowlist": ["C0351GJ62Q0"] },
+//     "commands": { "ping": "allow_all", "buns": { "denylist": ["C30L07P18"] } }
+//   }
+//
+// Commands without their own entry fall back to "default", and when the
+// variable isn't set at all every command is allowed everywhere.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ChannelPolicy {
+    #[default]
+    AllowAll,
+    Allowlist(Vec<String>),
+    Denylist(Vec<String>),
+    DmOnly,
+}
+
+impl ChannelPolicy {
+    pub fn allows(&self, channel: &str, is_direct_message: bool) -> bool {
+        match self {
+            ChannelPolicy::AllowAll => true,
+            ChannelPolicy::Allowlist(channels) => channels.iter().any(|c| c == channel),
+            ChannelPolicy::Denylist(channels) => !channels.iter().any(|c| c == channel),
+            ChannelPolicy::DmOnly => is_direct_message,
+        }
+    }
+
+    // Where the policy lets a command respond, in words, for !help.
+    pub fn describe(&self) -> String {
+        let mention_all = |channels: &[String]| -> String {
+            let mentions: Vec<String> = channels.iter().map(|c| format!("<#{}>", c)).collect();
+            mentions.join(", ")
+        };
+        match self {
+            ChannelPolicy::AllowAll => "every channel I'm in".to_string(),
+            ChannelPolicy::Allowlist(channels) if channels.is_empty() => "no channels".to_string(),
+            ChannelPolicy::Allowlist(channels) => format!("only {}", mention_all(channels)),
+            ChannelPolicy::Denylist(channels) if channels.is_empty() => {
+                "every channel I'm in".to_string()
+            }
+            ChannelPolicy::Denylist(channels) => {
+                format!("every channel I'm in except {}", mention_all(channels))
+            }
+            ChannelPolicy::DmOnly => "direct messages with me".to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+pub struct ChannelPolicies {
+    #[serde(default)]
+    pub default: ChannelPolicy,
+    #[serde(default)]
+    pub commands: HashMap<String, ChannelPolicy>,
+}
+
+impl ChannelPolicies {
+    pub fn from_json(json: &str) -> Result<Self, serde_json::Error> {
+        serde_json::from_str(json)
+    }
+
+    pub fn for_command(&self, name: &str) -> &ChannelPolicy {
+        self.commands.get(name).unwrap_or(&self.default)
+    }
+}
+
+// Slack doesn't always send channel_type, but DM channel IDs start with 'D'.
+pub fn is_direct_message(channel: &str, channel_type: Option<&str>) -> bool {
+    channel_type == Some("im") || channel.starts_with('D')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn applies_command_policy_before_default() {
+        let policies = ChannelPolicies::from_json(
+            r#"{
+                "default": { "allowlist": ["C0351GJ62Q0"] },
+                "commands": {
+                    "ping": "allow_all",
+                    "buns": { "denylist": ["C30L07P18"] },
+                    "secret": "dm_only"
+                }
+            }"#,
+        )
+        .unwrap();
+
+        assert!(policies.for_command("heart").allows("C0351GJ62Q0", false));
+        assert!(!policies.for_command("heart").allows("C2N5P84BD", false));
+        assert!(policies.for_command("ping").allows("C2N5P84BD", false));
+        assert!(policies.for_command("buns").allows("C2N5P84BD", false));
+        assert!(!policies.for_command("buns").allows("C30L07P18", false));
+        assert!(policies.for_command("secret").allows("D0123", true));
+        assert!(!policies.for_command("secret").allows("C0351GJ62Q0", false));
+        assert_eq!(
+            policies.for_command("heart").describe(),
+            "only <#C0351GJ62Q0>"
+        );
+        assert_eq!(
+            policies.for_command("buns").describe(),
+            "every channel I'm in except <#C30L07P18>"
+        );
+    }
+
+    #[test]
+    fn allows_everything_without_configuration() {
+        let policies = ChannelPolicies::from_json("{}").unwrap();
+        assert_eq!(policies, ChannelPolicies::default());
+        assert!(policies.for_command("ping").allows("C2N5P84BD", false));
+    }
+}
@@ -1,11 +1,574 @@
 // When you create new files under the commands directory
 // you will need to declare them as public modules below.
-// This will allow you to import them into main.rs.
+// To make a new command available, implement the Command trait
+// for it and register it in CommandRegistry::default().
 // Read more here: https://doc.rust-lang.org/rust-by-example/mod.html
 
+pub mod app_home;
 pub mod buns;
-pub mod chat_post_message;
-pub mod conversations_open;
 pub mod heart;
+pub mod help;
+pub mod karma;
 pub mod onboard_user;
+pub mod parser;
 pub mod ping;
+
+use crate::channels::{self, ChannelPolicies, ChannelPolicy};
+use crate::config::Config;
+use crate::dedup::EventStore;
+use crate::interactions::InteractionRouter;
+use crate::slack::api::{ChatPostMessageRequest, ReactionRequest, ResponseMessage};
+use crate::slack::blocks::Block;
+use crate::slack::client::{SlackClient, SlackError};
+use crate::slack::events::MessageEvent;
+use crate::slack::slash_commands::SlashCommand;
+use crate::store::Store;
+use crate::{worker, AppState};
+use async_trait::async_trait;
+use lambda_http::Error;
+use parser::{Invocation, ParseError, COMMAND_PREFIX};
+use std::fmt;
+
+// What made a command run.
+#[derive(Debug, Clone, Copy)]
+pub enum Trigger<'a> {
+    Message(&'a MessageEvent),
+    SlashCommand(&'a SlashCommand),
+}
+
+// Everything a command gets to know about the message or slash command that
+// triggered it.
+pub struct CommandContext<'a> {
+    pub slack: &'a SlackClient,
+    pub config: &'a Config,
+    pub store: &'a dyn Store,
+    // Remembers work already done for a trigger, see `claim`.
+    pub events: &'a EventStore,
+    // Every command DevilBot knows, e.g. for !help.
+    pub commands: &'a CommandRegistry,
+    pub trigger: Trigger<'a>,
+    // The channel the command was run in.
+    pub channel: &'a str,
+    pub user_id: &'a str,
+    // Present when the message was addressed to DevilBot, e.g. "!buns top 5".
+    // Always present for slash commands.
+    pub invocation: Option<Invocation>,
+}
+
+impl<'a> CommandContext<'a> {
+    pub fn new(
+        state: &'a AppState,
+        message: &'a MessageEvent,
+        user_id: &'a str,
+        invocation: Option<Invocation>,
+    ) -> Self {
+        CommandContext {
+            slack: &state.slack,
+            config: &state.config,
+            store: state.store.as_ref(),
+            events: &state.events,
+            commands: &state.commands,
+            trigger: Trigger::Message(message),
+            channel: &message.channel,
+            user_id,
+            invocation,
+        }
+    }
+
+    pub fn for_slash_command(
+        state: &'a AppState,
+        command: &'a SlashCommand,
+        invocation: Invocation,
+    ) -> Self {
+        CommandContext {
+            slack: &state.slack,
+            config: &state.config,
+            store: state.store.as_ref(),
+            events: &state.events,
+            commands: &state.commands,
+            trigger: Trigger::SlashCommand(command),
+            channel: &command.channel_id,
+            user_id: &command.user_id,
+            invocation: Some(invocation),
+        }
+    }
+
+    // The message that triggered the command, unless it was a slash command.
+    pub fn message(&self) -> Option<&'a MessageEvent> {
+        match self.trigger {
+            Trigger::Message(message) => Some(message),
+            Trigger::SlashCommand(_) => None,
+        }
+    }
+
+    // Records that `work` was done for this message or slash command, so a
+    // redelivered job doesn't do it again. Returns false if an earlier
+    // delivery already claimed it.
+    pub async fn claim(&self, work: &str) -> Result<bool, Error> {
+        self.events.claim(&self.work_id(work)).await
+    }
+
+    // Undoes a claim whose work failed, so the next delivery tries again.
+    pub async fn release(&self, work: &str) -> Result<(), Error> {
+        self.events.release(&self.work_id(work)).await
+    }
+
+    // The same for every delivery of the job: the message's channel and ts,
+    // or the slash command's trigger_id.
+    fn work_id(&self, work: &str) -> String {
+        match self.trigger {
+            Trigger::Message(message) => format!("{}#{}#{}", message.channel, message.ts, work),
+            Trigger::SlashCommand(command) => format!("{}#{}", command.trigger_id, work),
+        }
+    }
+
+    pub fn is_direct_message(&self) -> bool {
+        let channel_type: Option<&str> = self
+            .message()
+            .and_then(|message| message.channel_type.as_deref());
+        channels::is_direct_message(self.channel, channel_type)
+    }
+
+    // Posts in the channel itself: a new message for message commands, a
+    // response everyone can see for slash commands.
+    pub async fn say(&self, text: &str) -> Result<(), SlackError> {
+        match self.trigger {
+            Trigger::Message(message) => {
+                let request = ChatPostMessageRequest::new(&message.channel, text);
+                self.slack.chat_post_message(&request).await?;
+                Ok(())
+            }
+            Trigger::SlashCommand(command) => {
+                let response = ResponseMessage::in_channel(text);
+                self.slack.respond(&command.response_url, &response).await
+            }
+        }
+    }
+
+    // Replies in a thread under the message that triggered the command. Slash
+    // commands have no message, so they get a response everyone can see.
+    pub async fn reply(&self, text: &str) -> Result<(), SlackError> {
+        match self.trigger {
+            Trigger::Message(message) => reply(self.slack, message, text).await,
+            Trigger::SlashCommand(_) => self.say(text).await,
+        }
+    }
+
+    // Like reply, but rendered from Block Kit blocks. `text` is the
+    // fallback shown in notifications.
+    pub async fn reply_with_blocks(
+        &self,
+        text: &str,
+        blocks: Vec<Block>,
+    ) -> Result<(), SlackError> {
+        match self.trigger {
+            Trigger::Message(message) => {
+                let request = thread_reply(message, text).with_blocks(blocks);
+                self.slack.chat_post_message(&request).await?;
+                Ok(())
+            }
+            Trigger::SlashCommand(command) => {
+                let response = ResponseMessage::in_channel(text).with_blocks(blocks);
+                self.slack.respond(&command.response_url, &response).await
+            }
+        }
+    }
+
+    // Tells the user something about how they ran the command, like a usage
+    // error. Slash commands answer only the user; messages get a thread reply.
+    pub async fn report(&self, text: &str) -> Result<(), SlackError> {
+        match self.trigger {
+            Trigger::Message(message) => reply(self.slack, message, text).await,
+            Trigger::SlashCommand(command) => {
+                let response = ResponseMessage::ephemeral(text);
+                self.slack.respond(&command.response_url, &response).await
+            }
+        }
+    }
+
+    // Adds an emoji reaction (name without colons) to the message that
+    // triggered the command. Reacting twice with the same emoji is fine.
+    // Slash commands have nothing to react to, so the user gets the emoji
+    // as a response only they can see instead.
+    pub async fn react(&self, emoji: &str) -> Result<(), SlackError> {
+        let message: &MessageEvent = match self.trigger {
+            Trigger::Message(message) => message,
+            Trigger::SlashCommand(_) => return self.report(&format!(":{}:", emoji)).await,
+        };
+        let request = ReactionRequest {
+            channel: message.channel.clone(),
+            timestamp: message.ts.clone(),
+            name: emoji.to_string(),
+        };
+        match self.slack.reactions_add(&request).await {
+            Err(SlackError::Api { error, .. }) if error == "already_reacted" => Ok(()),
+            result => result,
+        }
+    }
+
+    // Positional arguments of the invocation, empty if there is none.
+    pub fn args(&self) -> &[String] {
+        self.invocation
+            .as_ref()
+            .map(|invocation| invocation.args.as_slice())
+            .unwrap_or_default()
+    }
+}
+
+// Returned by a command when it was invoked with arguments it doesn't
+// understand. The message is shown to the user along with the usage.
+#[derive(Debug)]
+pub struct UsageError(pub String);
+
+impl fmt::Display for UsageError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl std::error::Error for UsageError {}
+
+// Replies in a thread under the given message, or in its thread if it is
+// already part of one.
+pub async fn reply(
+    slack: &SlackClient,
+    message: &MessageEvent,
+    text: &str,
+) -> Result<(), SlackError> {
+    slack
+        .chat_post_message(&thread_reply(message, text))
+        .await?;
+    Ok(())
+}
+
+fn thread_reply(message: &MessageEvent, text: &str) -> ChatPostMessageRequest {
+    let thread_ts: &str = message.thread_ts.as_deref().unwrap_or(&message.ts);
+    ChatPostMessageRequest::new(&message.channel, text).in_thread(thread_ts)
+}
+
+#[async_trait]
+pub trait Command: Send + Sync {
+    fn name(&self) -> &'static str;
+
+    fn aliases(&self) -> &'static [&'static str] {
+        &[]
+    }
+
+    fn description(&self) -> &'static str;
+
+    // How to call the command, e.g. "!buns top [n]". Shown on usage errors.
+    fn usage(&self) -> String {
+        format!("{}{}", COMMAND_PREFIX, self.name())
+    }
+
+    // Decides whether this command should run for a message. By default a
+    // command runs when it is invoked by its name or one of its aliases.
+    fn matches(&self, ctx: &CommandContext) -> bool {
+        match &ctx.invocation {
+            Some(invocation) => {
+                invocation.name == self.name() || self.aliases().contains(&invocation.name.as_str())
+            }
+            None => false,
+        }
+    }
+
+    async fn execute(&self, ctx: &CommandContext<'_>) -> Result<(), Error>;
+
+    // Registers handlers for the buttons, menus and modals the command
+    // puts in its replies, see interactions.rs.
+    fn register_interactions(&self, _router: &mut InteractionRouter) {}
+}
+
+// The set of commands DevilBot responds to. Messages are dispatched to
+// every registered command whose matcher accepts them and whose channel
+// policy allows the channel the message was sent in.
+pub struct CommandRegistry {
+    commands: Vec<Box<dyn Command>>,
+    policies: ChannelPolicies,
+}
+
+impl CommandRegistry {
+    pub fn new() -> Self {
+        CommandRegistry {
+            commands: Vec::new(),
+            policies: ChannelPolicies::default(),
+        }
+    }
+
+    pub fn set_channel_policies(&mut self, policies: ChannelPolicies) -> &mut Self {
+        self.policies = policies;
+        self
+    }
+
+    pub fn policy_for(&self, name: &str) -> &ChannelPolicy {
+        self.policies.for_command(name)
+    }
+
+    // The policy for anything not tied to a single command, like telling the
+    // user their message couldn't be parsed.
+    pub fn default_policy(&self) -> &ChannelPolicy {
+        &self.policies.default
+    }
+
+    pub fn register(&mut self, command: impl Command + 'static) -> &mut Self {
+        self.commands.push(Box::new(command));
+        self
+    }
+
+    pub fn commands(&self) -> impl Iterator<Item = &dyn Command> {
+        self.commands.iter().map(|command| command.as_ref())
+    }
+
+    // Looks a command up by its name or one of its aliases.
+    pub fn find(&self, name: &str) -> Option<&dyn Command> {
+        self.commands()
+            .find(|command| command.name() == name || command.aliases().contains(&name))
+    }
+
+    // Turns a slash command into an invocation. A slash command named after
+    // one of ours, like "/buns top", runs it directly; any other, like
+    // "/devilbot buns top", names the command at the start of its text.
+    pub fn parse_slash_command(&self, command: &SlashCommand) -> Result<Invocation, ParseError> {
+        let name: String = command.name();
+        let text: String = match self.find(&name) {
+            Some(_) => format!("{}{} {}", COMMAND_PREFIX, name, command.text),
+            None => format!("{}{}", COMMAND_PREFIX, command.text),
+        };
+        parser::parse(&text, None)?.ok_or(ParseError::MissingCommand)
+    }
+
+    // Why dispatch would run nothing for the invocation, if it wouldn't.
+    // Slash commands are checked before they are queued, so the user hears
+    // about mistakes straight away.
+    pub fn refusal(&self, ctx: &CommandContext) -> Option<String> {
+        let name: &str = ctx
+            .invocation
+            .as_ref()
+            .map(|invocation| invocation.name.as_str())
+            .unwrap_or_default();
+        let mut matching = self
+            .commands()
+            .filter(|command| command.matches(ctx))
+            .peekable();
+        if matching.peek().is_none() {
+            return Some(unknown_command_text(name));
+        }
+        let is_direct_message: bool = ctx.is_direct_message();
+        if matching.any(|command| {
+            self.policy_for(command.name())
+                .allows(ctx.channel, is_direct_message)
+        }) {
+            None
+        } else {
+            Some(format!("`{}` isn't available in this channel.", name))
+        }
+    }
+
+    // Runs every command matching the message. A failing command is logged
+    // and doesn't stop the others from running. If it might work next time,
+    // dispatch fails once they are done so the job is retried, and the retry
+    // only runs the commands that haven't finished. Usage errors, and
+    // invocations no command recognizes, are reported back to the user.
+    pub async fn dispatch(&self, ctx: &CommandContext<'_>) -> Result<(), Error> {
+        let channel: &str = ctx.channel;
+        let is_direct_message: bool = ctx.is_direct_message();
+        let mut matched = false;
+        let mut failed: Vec<&str> = Vec::new();
+        for command in self.commands().filter(|command| command.matches(ctx)) {
+            matched = true;
+            if !self
+                .policy_for(command.name())
+                .allows(channel, is_direct_message)
+            {
+                log::info!("Command {} is not allowed in {}", command.name(), channel);
+                continue;
+            }
+            if !ctx.claim(command.name()).await? {
+                log::info!("Command {} already ran", command.name());
+                continue;
+            }
+            log::info!("Running command {}", command.name());
+            match command.execute(ctx).await {
+                Ok(()) => {}
+                Err(err) => match err.downcast_ref::<UsageError>() {
+                    Some(usage_error) => {
+                        let text = format!("{}\nUsage: `{}`", usage_error, command.usage());
+                        if let Err(err) = ctx.report(&text).await {
+                            log::info!("Could not report usage error: {}", err);
+                        }
+                    }
+                    None if worker::is_permanent(&err) => {
+                        log::error!("Command {} failed for good: {}", command.name(), err);
+                    }
+                    None => {
+                        log::info!("Command {} failed: {}", command.name(), err);
+                        ctx.release(command.name()).await?;
+                        failed.push(command.name());
+                    }
+                },
+            }
+        }
+        let may_reply: bool = self.default_policy().allows(channel, is_direct_message);
+        if let (false, true, Some(invocation)) = (matched, may_reply, &ctx.invocation) {
+            let text = unknown_command_text(&invocation.name);
+            if let Err(err) = ctx.report(&text).await {
+                log::info!("Could not report unknown command: {}", err);
+            }
+        }
+        if failed.is_empty() {
+            Ok(())
+        } else {
+            Err(format!("commands failed: {}", failed.join(", ")).into())
+        }
+    }
+}
+
+fn unknown_command_text(name: &str) -> String {
+    format!("I don't know the command `{}`.", name)
+}
+
+impl Default for CommandRegistry {
+    fn default() -> Self {
+        let mut registry = CommandRegistry::new();
+        registry
+            .register(ping::Ping)
+            .register(buns::Buns)
+            .register(heart::Heart)
+            .register(karma::Karma)
+            .register(help::Help);
+        registry
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dedup::EventStore;
+    use crate::store::memory::MemoryStore;
+    use serde_json::json;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    struct Counting {
+        runs: Arc<AtomicUsize>,
+    }
+
+    #[async_trait]
+    impl Command for Counting {
+        fn name(&self) -> &'static str {
+            "count"
+        }
+
+        fn aliases(&self) -> &'static [&'static str] {
+            &["tally"]
+        }
+
+        fn description(&self) -> &'static str {
+            "Counts how often it runs."
+        }
+
+        async fn execute(&self, _ctx: &CommandContext<'_>) -> Result<(), Error> {
+            self.runs.fetch_add(1, Ordering::SeqCst);
+            Ok(())
+        }
+    }
+
+    // The test commands never touch the Slack client.
+    fn test_state() -> AppState {
+        let config = Config::from_vars(|name| match name {
+            "SLACK_API_BOT_TOKEN" => Some("xoxb-test".to_string()),
+            "SLACK_SIGNING_SECRET" => Some("secret".to_string()),
+            "BUNS_TABLE_NAME" => Some("buns".to_string()),
+            "KARMA_TABLE_NAME" => Some("karma".to_string()),
+            "KARMA_HISTORY_TABLE_NAME" => Some("karma-history".to_string()),
+            _ => None,
+        })
+        .unwrap();
+        AppState {
+            slack: SlackClient::new(config.slack_bot_token.clone()),
+            store: Arc::new(MemoryStore::default()),
+            config,
+            events: EventStore::new(Arc::new(MemoryStore::default()), "events".to_string()),
+            queue: Arc::new(crate::queue::local::channel().0),
+            commands: CommandRegistry::new(),
+            interactions: InteractionRouter::default(),
+        }
+    }
+
+    fn context_for<'a>(state: &'a AppState, message: &'a MessageEvent) -> CommandContext<'a> {
+        let invocation = parser::parse(&message.text, None).unwrap();
+        CommandContext::new(state, message, "U123", invocation)
+    }
+
+    fn message(text: &str) -> MessageEvent {
+        serde_json::from_value(json!({
+            "channel": "C0351GJ62Q0",
+            "user": "U123",
+            "text": text,
+            "ts": "1645903860.916719",
+        }))
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn dispatches_by_name_and_alias() {
+        let state = test_state();
+        let runs = Arc::new(AtomicUsize::new(0));
+        let mut registry = CommandRegistry::new();
+        registry.register(Counting { runs: runs.clone() });
+
+        for (n, text) in ["!count", " !TALLY ", "count me in", "!tally me in"]
+            .into_iter()
+            .enumerate()
+        {
+            let mut message = message(text);
+            message.ts = format!("1645903860.91672{}", n);
+            registry
+                .dispatch(&context_for(&state, &message))
+                .await
+                .unwrap();
+        }
+
+        assert_eq!(runs.load(Ordering::SeqCst), 3);
+        assert_eq!(registry.find("tally").map(|c| c.name()), Some("count"));
+        assert!(registry.find("nope").is_none());
+    }
+
+    #[tokio::test]
+    async fn runs_commands_once_per_message() {
+        let state = test_state();
+        let runs = Arc::new(AtomicUsize::new(0));
+        let mut registry = CommandRegistry::new();
+        registry.register(Counting { runs: runs.clone() });
+
+        let message = message("!count");
+        for _ in 0..2 {
+            registry
+                .dispatch(&context_for(&state, &message))
+                .await
+                .unwrap();
+        }
+
+        assert_eq!(runs.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn skips_commands_outside_their_channels() {
+        let state = test_state();
+        let runs = Arc::new(AtomicUsize::new(0));
+        let mut registry = CommandRegistry::new();
+        registry
+            .register(Counting { runs: runs.clone() })
+            .set_channel_policies(
+                ChannelPolicies::from_json(r#"{"default": {"allowlist": ["C2N5P84BD"]}}"#).unwrap(),
+            );
+
+        let message = message("!count");
+        registry
+            .dispatch(&context_for(&state, &message))
+            .await
+            .unwrap();
+
+        assert_eq!(runs.load(Ordering::SeqCst), 0);
+    }
+}
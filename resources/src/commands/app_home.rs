@@ -0,0 +1,208 @@
+use crate::channels::ChannelPolicy;
+use crate::commands::{buns, karma, CommandRegistry};
+use crate::interactions::{InteractionContext, InteractionHandler, InteractionRouter};
+use crate::slack::api::{HomeView, ViewsPublishRequest};
+use crate::slack::blocks::{Block, Button, Context, Divider, Header, Section, Text};
+use crate::store::{Item, Query};
+use crate::AppState;
+use async_trait::async_trait;
+use lambda_http::Error;
+
+// How many upcoming club events App Home lists.
+const MAX_CLUB_EVENTS: usize = 5;
+
+// Every event's Details button shares this action_id, and the block_id
+// tells them apart.
+const CLUB_EVENT_ACTION_ID: &str = "club_event_details";
+
+// A meeting or event from the club events table. Officers add them by hand:
+//
+//   event_id   any unique string
+//   title      "Hack Night"
+//   starts_at  unix timestamp, a number
+//   location   optional, e.g. "BYENG 210"
+//   url        optional, e.g. the sign-up form
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct ClubEvent {
+    title: String,
+    starts_at: i64,
+    location: Option<String>,
+    url: Option<String>,
+}
+
+impl ClubEvent {
+    fn from_item(item: &Item) -> Option<Self> {
+        let optional = |name: &str| {
+            item.get(name)
+                .and_then(|value| value.as_str())
+                .map(String::from)
+        };
+        Some(ClubEvent {
+            title: item.get("title")?.as_str()?.to_string(),
+            starts_at: item.get("starts_at")?.as_i64()?,
+            location: optional("location"),
+            url: optional("url"),
+        })
+    }
+}
+
+// Events that haven't started yet, soonest first.
+fn upcoming(items: &[Item], now: i64) -> Vec<ClubEvent> {
+    let mut events: Vec<ClubEvent> = items
+        .iter()
+        .filter_map(ClubEvent::from_item)
+        .filter(|event| event.starts_at >= now)
+        .collect();
+    events.sort_by_key(|event| event.starts_at);
+    events.truncate(MAX_CLUB_EVENTS);
+    events
+}
+
+fn home_blocks(
+    buns: i64,
+    karma: i64,
+    events: &[ClubEvent],
+    commands: &CommandRegistry,
+) -> Vec<Block> {
+    let mut blocks: Vec<Block> = vec![
+        Header::new("Your stats").into(),
+        Section::fields(vec![
+            Text::mrkdwn(format!("*Buns*\n{} :buns:", buns)),
+            Text::mrkdwn(format!("*Karma*\n{}", karma)),
+        ])
+        .into(),
+        Divider::default().into(),
+        Header::new("Upcoming events").into(),
+    ];
+    if events.is_empty() {
+        blocks.push(
+            Section::new(Text::mrkdwn(
+                "Nothing scheduled right now, check back soon!",
+            ))
+            .into(),
+        );
+    }
+    for (index, event) in events.iter().enumerate() {
+        // Slack shows the date in each reader's own time zone.
+        let mut text = format!(
+            "*{}*\n<!date^{}^{{date_short_pretty}} at {{time}}|soon>",
+            event.title, event.starts_at
+        );
+        if let Some(location) = &event.location {
+            text.push_str(&format!(" · {}", location));
+        }
+        let mut section =
+            Section::new(Text::mrkdwn(text)).block_id(format!("club_event_{}", index));
+        if let Some(url) = &event.url {
+            section = section.accessory(Button::new("Details", CLUB_EVENT_ACTION_ID).url(url));
+        }
+        blocks.push(section.into());
+    }
+    blocks.push(Divider::default().into());
+    blocks.push(Header::new("Commands").into());
+    for command in commands.commands() {
+        let text = format!("`{}`\n{}", command.usage(), command.description());
+        blocks.push(Section::new(Text::mrkdwn(text)).into());
+    }
+    let hint = where_commands_work(commands.default_policy());
+    blocks.push(Context::new(vec![Text::mrkdwn(hint).into()]).into());
+    blocks
+}
+
+// Where commands work, as !help puts it, and whether that includes the
+// Messages tab right next to App Home.
+fn where_commands_work(policy: &ChannelPolicy) -> String {
+    match policy {
+        ChannelPolicy::DmOnly => "Send commands right here in Messages.".to_string(),
+        policy if policy.allows("", true) => format!(
+            "Send commands in {}, or right here in Messages.",
+            policy.describe()
+        ),
+        policy => format!("Send commands in {}.", policy.describe()),
+    }
+}
+
+// Slack sends an interaction for link buttons too, even though the browser
+// does all the work. Acknowledging it keeps it out of the unhandled log.
+struct OpenClubEvent;
+
+#[async_trait]
+impl InteractionHandler for OpenClubEvent {
+    async fn handle(&self, _ctx: &InteractionContext<'_>) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
+pub fn register_interactions(router: &mut InteractionRouter) {
+    router.register(CLUB_EVENT_ACTION_ID, OpenClubEvent);
+}
+
+// Publishes the user's App Home tab. It is rebuilt every time they open it,
+// so it is always up to date.
+pub async fn publish(state: &AppState, user_id: &str) -> Result<(), Error> {
+    let store = state.store.as_ref();
+    let buns: i64 = buns::count(store, &state.config, user_id).await?;
+    let karma: i64 = karma::user_score(store, &state.config, user_id).await?;
+    let events: Vec<ClubEvent> = match &state.config.club_events_table_name {
+        Some(table) => upcoming(&store.query(table, &Query::Scan).await?, crate::unix_now()),
+        None => Vec::new(),
+    };
+
+    let request = ViewsPublishRequest {
+        user_id: user_id.to_string(),
+        view: HomeView {
+            blocks: home_blocks(buns, karma, &events, &state.commands),
+            callback_id: Some("app_home".to_string()),
+        },
+    };
+    state.slack.views_publish(&request).await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::slack::blocks::{self, MAX_VIEW_BLOCKS};
+    use crate::store::Value;
+
+    fn event(title: &str, starts_at: i64) -> Item {
+        Item::from([
+            ("event_id".to_string(), Value::from(title)),
+            ("title".to_string(), Value::from(title)),
+            ("starts_at".to_string(), Value::from(starts_at)),
+        ])
+    }
+
+    #[test]
+    fn lists_upcoming_events_soonest_first() {
+        let items = vec![
+            event("Later", 300),
+            event("Over", 100),
+            event("Next", 200),
+            Item::from([("title".to_string(), Value::from("No date"))]),
+        ];
+
+        let events = upcoming(&items, 150);
+
+        let titles: Vec<&str> = events.iter().map(|event| event.title.as_str()).collect();
+        assert_eq!(titles, vec!["Next", "Later"]);
+        let blocks = home_blocks(3, -1, &events, &CommandRegistry::default());
+        assert_eq!(blocks::validate(&blocks, MAX_VIEW_BLOCKS), Ok(()));
+    }
+
+    #[test]
+    fn says_where_commands_work() {
+        assert_eq!(
+            where_commands_work(&ChannelPolicy::AllowAll),
+            "Send commands in every channel I'm in, or right here in Messages."
+        );
+        assert_eq!(
+            where_commands_work(&ChannelPolicy::Allowlist(vec!["C0351GJ62Q0".to_string()])),
+            "Send commands in only <#C0351GJ62Q0>."
+        );
+        assert_eq!(
+            where_commands_work(&ChannelPolicy::DmOnly),
+            "Send commands right here in Messages."
+        );
+    }
+}
@@ -1,25 +1,263 @@
-use crate::increment_buns;
-use slack_hook::{PayloadBuilder, Slack};
-
-// This function parses the text event from the slack event subscription
-// if the text contains any form of "buns", it will respond with "You are buns".
-// This can be used as an example command when creating new commands for
-// the Slack bot.
-// TODO: respond with just a buns emoji to the message
-pub async fn run(channel: &str, enterprise_user_id: &str) {
-    increment_buns(enterprise_user_id).await;
-    let slack_webhook_url: String = crate::get_env_var(crate::DEVIL_BOT_TEST_CHANNEL_URL);
-    let slack = Slack::new(&*slack_webhook_url).unwrap();
-    let p = PayloadBuilder::new()
-        .text("You are :buns:")
-        .channel(channel)
-        .username("DevilBot")
-        .build()
-        .unwrap();
-
-    let res: Result<(), slack_hook::Error> = slack.send(&p);
-    match res {
-        Ok(()) => log::info!("Slack message sent successfully."),
-        Err(x) => log::info!("ERR: {}", x),
+use crate::commands::{Command, CommandContext, UsageError};
+use crate::config::Config;
+use crate::interactions::{InteractionContext, InteractionHandler, InteractionRouter};
+use crate::slack::api::ResponseMessage;
+use crate::slack::blocks::{Actions, Block, Button, Header, Section, Text};
+use crate::slack::interactions::Interaction;
+use crate::store::{Item, Key, Query, Store};
+use async_trait::async_trait;
+use lambda_http::Error;
+
+// How many people "!buns top" shows without a count, and at most.
+const DEFAULT_TOP: usize = 10;
+const MAX_TOP: usize = 25;
+
+const REFRESH_TOP_ACTION_ID: &str = "buns_refresh_top";
+
+// Claimed once a message's bun has been counted, see CommandContext::claim.
+const GIVE_CLAIM: &str = "buns_give";
+
+// Runs on "!buns". On its own it adds one to the sender's buns count and
+// reacts to their message with a buns emoji. It can also show the counts:
+//
+//   !buns top [n]   the n people with the most buns
+//   !buns me        the sender's count and rank
+//   !buns @user     someone else's count and rank
+pub struct Buns;
+
+#[derive(Debug, PartialEq, Eq)]
+enum Request {
+    Give,
+    Top(usize),
+    Count(String),
+}
+
+// One row of the buns table.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct BunsCount {
+    user_id: String,
+    buns: i64,
+}
+
+impl BunsCount {
+    fn from_item(item: &Item) -> Option<Self> {
+        Some(BunsCount {
+            user_id: item.get("user_id")?.as_str()?.to_string(),
+            buns: item.get("buns").and_then(|buns| buns.as_i64()).unwrap_or(0),
+        })
+    }
+}
+
+fn parse_request(
+    args: &[String],
+    user_mentions: &[String],
+    sender: &str,
+) -> Result<Request, UsageError> {
+    let args: Vec<String> = args.iter().map(|arg| arg.to_lowercase()).collect();
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+    match (args.as_slice(), user_mentions) {
+        ([], []) => Ok(Request::Give),
+        ([], [user_id]) => Ok(Request::Count(user_id.clone())),
+        (["me"], []) => Ok(Request::Count(sender.to_string())),
+        (["top"], []) => Ok(Request::Top(DEFAULT_TOP)),
+        (["top", n], []) => match n.parse::<usize>() {
+            Ok(n) if (1..=MAX_TOP).contains(&n) => Ok(Request::Top(n)),
+            _ => Err(UsageError(format!(
+                "`{}` isn't a number from 1 to {}.",
+                n, MAX_TOP
+            ))),
+        },
+        _ => Err(UsageError(
+            "I can show the top counts, yours, or one person's.".to_string(),
+        )),
+    }
+}
+
+// Everyone with buns, most first. Ties are broken by user ID so the order
+// is stable between calls.
+fn rank(items: &[Item]) -> Vec<BunsCount> {
+    let mut counts: Vec<BunsCount> = items.iter().filter_map(BunsCount::from_item).collect();
+    counts.sort_by(|a, b| b.buns.cmp(&a.buns).then_with(|| a.user_id.cmp(&b.user_id)));
+    counts
+}
+
+// The leaderboard's top n, with a button to fetch it again.
+fn leaderboard_blocks(counts: &[BunsCount], n: usize) -> Vec<Block> {
+    let lines: Vec<String> = counts
+        .iter()
+        .enumerate()
+        .map(|(index, count)| format!("{}. <@{}> {} :buns:", index + 1, count.user_id, count.buns))
+        .collect();
+    let body: String = if lines.is_empty() {
+        "Nobody has any buns yet. Say `!buns` to get the first one!".to_string()
+    } else {
+        lines.join("\n")
+    };
+    let refresh = Button::new("Refresh", REFRESH_TOP_ACTION_ID).value(n.to_string());
+    vec![
+        Header::new("Buns leaderboard").into(),
+        Section::new(Text::mrkdwn(body)).into(),
+        Actions::new(vec![refresh.into()]).into(),
+    ]
+}
+
+// Someone's buns count, for their App Home.
+pub(crate) async fn count(store: &dyn Store, config: &Config, user_id: &str) -> Result<i64, Error> {
+    let key = Key::new("user_id", user_id);
+    let item = store.get(&config.buns_table_name, &key).await?;
+    Ok(item
+        .as_ref()
+        .and_then(BunsCount::from_item)
+        .map_or(0, |count| count.buns))
+}
+
+async fn top(store: &dyn Store, table: &str, n: usize) -> Result<Vec<Block>, Error> {
+    let counts = rank(&store.query(table, &Query::Scan).await?);
+    Ok(leaderboard_blocks(&counts[..counts.len().min(n)], n))
+}
+
+fn count_text(counts: &[BunsCount], user_id: &str) -> String {
+    match counts.iter().position(|count| count.user_id == user_id) {
+        Some(index) => format!(
+            "<@{}> has {} :buns:, number {} of {}.",
+            user_id,
+            counts[index].buns,
+            index + 1,
+            counts.len()
+        ),
+        None => format!("<@{}> doesn't have any :buns: yet.", user_id),
+    }
+}
+
+#[async_trait]
+impl Command for Buns {
+    fn name(&self) -> &'static str {
+        "buns"
+    }
+
+    fn description(&self) -> &'static str {
+        "Gives you one more :buns:, or shows who has the most."
+    }
+
+    fn usage(&self) -> String {
+        "!buns [top [n] | me | @user]".to_string()
+    }
+
+    async fn execute(&self, ctx: &CommandContext<'_>) -> Result<(), Error> {
+        let user_mentions: &[String] = ctx
+            .invocation
+            .as_ref()
+            .map(|invocation| invocation.user_mentions.as_slice())
+            .unwrap_or_default();
+        let table: &str = &ctx.config.buns_table_name;
+        match parse_request(ctx.args(), user_mentions, ctx.user_id)? {
+            Request::Give => {
+                // Counted once per message, even if the reaction fails and
+                // the job is retried.
+                if ctx.claim(GIVE_CLAIM).await? {
+                    let key = Key::new("user_id", ctx.user_id);
+                    match ctx.store.increment(table, &key, "buns", 1).await {
+                        Ok(total) => log::info!("{} now has {} buns", ctx.user_id, total),
+                        Err(err) => {
+                            ctx.release(GIVE_CLAIM).await?;
+                            return Err(err);
+                        }
+                    }
+                }
+                ctx.react("buns").await?;
+            }
+            Request::Top(n) => {
+                let blocks = top(ctx.store, table, n).await?;
+                ctx.reply_with_blocks("Buns leaderboard", blocks).await?;
+            }
+            Request::Count(user_id) => {
+                let counts = rank(&ctx.store.query(table, &Query::Scan).await?);
+                ctx.reply(&count_text(&counts, &user_id)).await?;
+            }
+        }
+        Ok(())
+    }
+
+    fn register_interactions(&self, router: &mut InteractionRouter) {
+        router.register(REFRESH_TOP_ACTION_ID, RefreshTop);
+    }
+}
+
+// The leaderboard's Refresh button. It replaces the leaderboard with the
+// current counts, for the same n.
+struct RefreshTop;
+
+#[async_trait]
+impl InteractionHandler for RefreshTop {
+    async fn handle(&self, ctx: &InteractionContext<'_>) -> Result<(), Error> {
+        let n: usize = match ctx.interaction {
+            Interaction::BlockActions(actions) => actions
+                .actions
+                .iter()
+                .find(|action| action.action_id == REFRESH_TOP_ACTION_ID)
+                .and_then(|action| action.value.as_deref()?.parse().ok())
+                .unwrap_or(DEFAULT_TOP)
+                .min(MAX_TOP),
+            _ => return Ok(()),
+        };
+        let blocks = top(ctx.store, &ctx.config.buns_table_name, n).await?;
+        let response = ResponseMessage::replacing_original("Buns leaderboard").with_blocks(blocks);
+        ctx.respond(&response).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::store::Value as StoreValue;
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    fn item(user_id: &str, buns: i64) -> Item {
+        Item::from([
+            ("user_id".to_string(), StoreValue::from(user_id)),
+            ("buns".to_string(), StoreValue::N(buns)),
+        ])
+    }
+
+    #[test]
+    fn parses_requests() {
+        let parse = |a: &[&str], mentions: &[&str]| parse_request(&args(a), &args(mentions), "U1");
+        assert_eq!(parse(&[], &[]).unwrap(), Request::Give);
+        assert_eq!(parse(&["TOP"], &[]).unwrap(), Request::Top(DEFAULT_TOP));
+        assert_eq!(parse(&["top", "3"], &[]).unwrap(), Request::Top(3));
+        assert_eq!(
+            parse(&["me"], &[]).unwrap(),
+            Request::Count("U1".to_string())
+        );
+        assert_eq!(
+            parse(&[], &["U2"]).unwrap(),
+            Request::Count("U2".to_string())
+        );
+        assert!(parse(&["top", "0"], &[]).is_err());
+        assert!(parse(&["top", "lots"], &[]).is_err());
+        assert!(parse(&["everyone"], &[]).is_err());
+    }
+
+    #[test]
+    fn ranks_by_count_then_user() {
+        let counts = rank(&[item("U3", 2), item("U1", 5), item("U2", 2)]);
+        let order: Vec<&str> = counts.iter().map(|c| c.user_id.as_str()).collect();
+        assert_eq!(order, vec!["U1", "U2", "U3"]);
+
+        assert_eq!(
+            leaderboard_blocks(&counts[..2], 2)[1],
+            Section::new(Text::mrkdwn("1. <@U1> 5 :buns:\n2. <@U2> 2 :buns:")).into()
+        );
+        assert_eq!(
+            count_text(&counts, "U2"),
+            "<@U2> has 2 :buns:, number 2 of 3."
+        );
+        assert_eq!(
+            count_text(&counts, "U9"),
+            "<@U9> doesn't have any :buns: yet."
+        );
     }
 }
@@ -1,22 +1,22 @@
-// This function will add a heart reaction to the passed in message
-// Send a Heart emoji to the channel with the specified timestamp from the message
-use slack_hook::{PayloadBuilder, Slack};
+// This command adds a heart reaction to the message that invoked it.
+use crate::commands::{Command, CommandContext};
+use async_trait::async_trait;
+use lambda_http::Error;
 
-// TODO Use reactions.add
-pub async fn run(channel: &str) {
-    let slack_webhook_url: String = crate::get_env_var(crate::DEVIL_BOT_TEST_CHANNEL_URL);
-    let slack = Slack::new(&*slack_webhook_url).unwrap();
-    let p = PayloadBuilder::new()
-        .text("This message should have a heart? :heart:")
-        .icon_emoji(":heart:")
-        .channel(channel)
-        .username("DevilBot")
-        .build()
-        .unwrap();
+pub struct Heart;
 
-    let res: Result<(), slack_hook::Error> = slack.send(&p);
-    match res {
-        Ok(()) => log::info!("Slack message sent successfully."),
-        Err(x) => log::info!("ERR: {}", x),
+#[async_trait]
+impl Command for Heart {
+    fn name(&self) -> &'static str {
+        "heart"
+    }
+
+    fn description(&self) -> &'static str {
+        "Shows the message some love with a :heart: reaction."
+    }
+
+    async fn execute(&self, ctx: &CommandContext<'_>) -> Result<(), Error> {
+        ctx.react("heart").await?;
+        Ok(())
     }
 }
@@ -0,0 +1,116 @@
+use crate::commands::parser::COMMAND_PREFIX;
+use crate::commands::{Command, CommandContext, CommandRegistry, UsageError};
+use crate::slack::blocks::{Block, Context, Header, Section, Text};
+use async_trait::async_trait;
+use lambda_http::Error;
+
+// Lists what DevilBot can do, built from the registered commands so it never
+// goes stale:
+//
+//   !help            every command
+//   !help <command>  one command, also found by its aliases
+pub struct Help;
+
+// A command's usage, description, aliases and the channels it works in.
+fn describe(command: &dyn Command, registry: &CommandRegistry) -> String {
+    let mut lines: Vec<String> = vec![
+        format!("*`{}`*", command.usage()),
+        command.description().to_string(),
+    ];
+    if !command.aliases().is_empty() {
+        let aliases: Vec<String> = command
+            .aliases()
+            .iter()
+            .map(|alias| format!("`{}{}`", COMMAND_PREFIX, alias))
+            .collect();
+        lines.push(format!("Also: {}", aliases.join(", ")));
+    }
+    let policy = registry.policy_for(command.name());
+    lines.push(format!("_Works in {}._", policy.describe()));
+    lines.join("\n")
+}
+
+fn help_blocks(registry: &CommandRegistry) -> Vec<Block> {
+    let mut blocks: Vec<Block> = vec![Header::new("What I can do").into()];
+    for command in registry.commands() {
+        blocks.push(Section::new(Text::mrkdwn(describe(command, registry))).into());
+    }
+    let hint = format!("Say `{}help <command>` to see just one.", COMMAND_PREFIX);
+    blocks.push(Context::new(vec![Text::mrkdwn(hint).into()]).into());
+    blocks
+}
+
+#[async_trait]
+impl Command for Help {
+    fn name(&self) -> &'static str {
+        "help"
+    }
+
+    fn aliases(&self) -> &'static [&'static str] {
+        &["commands"]
+    }
+
+    fn description(&self) -> &'static str {
+        "Lists everything DevilBot can do, or explains one command."
+    }
+
+    fn usage(&self) -> String {
+        "!help [command]".to_string()
+    }
+
+    async fn execute(&self, ctx: &CommandContext<'_>) -> Result<(), Error> {
+        let registry: &CommandRegistry = ctx.commands;
+        match ctx.args() {
+            [] => {
+                ctx.reply_with_blocks("What I can do", help_blocks(registry))
+                    .await?
+            }
+            [name] => {
+                let name: String = name.trim_start_matches(COMMAND_PREFIX).to_lowercase();
+                let command = registry
+                    .find(&name)
+                    .ok_or_else(|| UsageError(format!("I don't know the command `{}`.", name)))?;
+                ctx.reply(&describe(command, registry)).await?
+            }
+            _ => {
+                return Err(
+                    UsageError("I can only explain one command at a time.".to_string()).into(),
+                )
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::channels::ChannelPolicies;
+    use crate::slack::blocks::{self, MAX_MESSAGE_BLOCKS};
+
+    #[test]
+    fn describes_every_registered_command() {
+        let mut registry = CommandRegistry::default();
+        registry.set_channel_policies(
+            ChannelPolicies::from_json(r#"{"commands": {"buns": "dm_only"}}"#).unwrap(),
+        );
+
+        let blocks = help_blocks(&registry);
+
+        assert_eq!(blocks.len(), registry.commands().count() + 2);
+        assert_eq!(blocks::validate(&blocks, MAX_MESSAGE_BLOCKS), Ok(()));
+        assert_eq!(
+            describe(registry.find("buns").unwrap(), &registry),
+            "*`!buns [top [n] | me | @user]`*\n\
+            Gives you one more :buns:, or shows who has the most.\n\
+            _Works in direct messages with me._"
+        );
+        assert_eq!(
+            describe(&Help, &registry),
+            "*`!help [command]`*\n\
+            Lists everything DevilBot can do, or explains one command.\n\
+            Also: `!commands`\n\
+            _Works in every channel I'm in._"
+        );
+    }
+}
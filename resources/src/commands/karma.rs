@@ -0,0 +1,388 @@
+use crate::commands::parser::parse_mention;
+use crate::commands::{Command, CommandContext};
+use crate::config::Config;
+use crate::store::{Item, Key, Query, Store, Value};
+use async_trait::async_trait;
+use lambda_http::Error;
+
+// Runs on any message that gives or takes karma:
+//
+//   <@U123>++   <@U123> --   bagels++
+//
+// Totals live in the karma table keyed by `target`, which is a user ID for
+// people and the lowercased word for things. Slack user IDs are uppercase,
+// so the two can't collide. Every change is also recorded in the karma
+// history table under the giver, which is what rate limiting reads.
+// "!karma", "!karma @user" and "!karma thing" show a score.
+pub struct Karma;
+
+// How many karma changes one person may make per window.
+const MAX_CHANGES_PER_WINDOW: usize = 10;
+const WINDOW_SECS: i64 = 60 * 60;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Target {
+    User(String),
+    Thing(String),
+}
+
+impl Target {
+    fn key(&self) -> &str {
+        match self {
+            Target::User(user_id) => user_id,
+            Target::Thing(thing) => thing,
+        }
+    }
+
+    fn display(&self) -> String {
+        match self {
+            Target::User(user_id) => format!("<@{}>", user_id),
+            Target::Thing(thing) => thing.clone(),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+struct Change {
+    target: Target,
+    delta: i64,
+}
+
+// Finds every ++ and -- in a message. Each target counts once per message,
+// and anything inside `inline code` is ignored so snippets like `i++`
+// don't hand out karma.
+fn parse_changes(text: &str) -> Vec<Change> {
+    let mut operators: Vec<(usize, i64)> = text
+        .match_indices("++")
+        .map(|(index, _)| (index, 1))
+        .chain(text.match_indices("--").map(|(index, _)| (index, -1)))
+        .collect();
+    operators.sort();
+
+    let mut changes: Vec<Change> = Vec::new();
+    for (index, delta) in operators {
+        let (before, after) = (&text[..index], &text[index + 2..]);
+        let in_code = before.matches('`').count() % 2 == 1;
+        let ends_word = after
+            .chars()
+            .next()
+            .is_none_or(|next| next.is_whitespace() || ".,!?;:)".contains(next));
+        if in_code || !ends_word {
+            continue;
+        }
+        let target = match target_before(before) {
+            Some(target) => target,
+            None => continue,
+        };
+        if !changes.iter().any(|change| change.target == target) {
+            changes.push(Change { target, delta });
+        }
+    }
+    changes
+}
+
+// A mention may be separated from its operator by spaces, a plain word
+// may not. Single letters are left alone, since those are languages like
+// C++ and g++ rather than things anyone means to give karma to.
+fn target_before(before: &str) -> Option<Target> {
+    let trimmed = before.trim_end();
+    if trimmed.ends_with('>') {
+        let start = trimmed.rfind("<@")?;
+        return parse_mention(&trimmed[start..], "<@").map(Target::User);
+    }
+    let is_word_char = |c: char| c.is_alphanumeric() || c == '_';
+    let start = before
+        .char_indices()
+        .rev()
+        .take_while(|(_, c)| is_word_char(*c))
+        .last()
+        .map(|(index, _)| index)?;
+    let thing: &str = &before[start..];
+    if thing.chars().count() < 2 {
+        return None;
+    }
+    Some(Target::Thing(thing.to_lowercase()))
+}
+
+// Applies the changes for one message and describes the outcome, one line
+// per change.
+async fn apply_changes(
+    store: &dyn Store,
+    config: &Config,
+    giver: &str,
+    message_ts: &str,
+    changes: &[Change],
+    now: i64,
+) -> Result<Vec<String>, Error> {
+    // Slack message timestamps are unix seconds, so history keys sort by
+    // time as strings.
+    let recent = Query::Partition {
+        name: "giver".to_string(),
+        value: Value::from(giver),
+        sort_from: Some((
+            "given_at".to_string(),
+            Value::S((now - WINDOW_SECS).to_string()),
+        )),
+    };
+    // Changes this message already made, on an earlier delivery of the same
+    // job, don't count against it.
+    let this_message: String = format!("{}#", message_ts);
+    let recent_changes: usize = store
+        .query(&config.karma_history_table_name, &recent)
+        .await?
+        .iter()
+        .filter(|item| {
+            !item
+                .get("given_at")
+                .and_then(Value::as_str)
+                .is_some_and(|given_at| given_at.starts_with(&this_message))
+        })
+        .count();
+    let mut allowance: usize = MAX_CHANGES_PER_WINDOW.saturating_sub(recent_changes);
+
+    let mut lines: Vec<String> = Vec::new();
+    for change in changes {
+        if change.target == Target::User(giver.to_string()) {
+            lines.push("Nice try, but you can't change your own karma.".to_string());
+            continue;
+        }
+        if allowance == 0 {
+            lines.push(
+                "You've handed out a lot of karma lately, give it a rest for a bit.".to_string(),
+            );
+            break;
+        }
+        allowance -= 1;
+
+        // History goes first, and only if this message hasn't recorded the
+        // change yet, so a redelivered message doesn't count twice.
+        let history_key = Key::new("giver", giver).with_sort(
+            "given_at",
+            format!("{}{}", this_message, change.target.key()),
+        );
+        let mut history = Item::new();
+        history.insert("target".to_string(), Value::from(change.target.key()));
+        history.insert("delta".to_string(), Value::N(change.delta));
+        let recorded: bool = store
+            .put_if_absent(&config.karma_history_table_name, &history_key, history)
+            .await?;
+        let total: i64 = if recorded {
+            let key = Key::new("target", change.target.key());
+            store
+                .increment(&config.karma_table_name, &key, "karma", change.delta)
+                .await?
+        } else {
+            score(store, config, &change.target).await?
+        };
+        lines.push(format!(
+            "{} now has {} karma.",
+            change.target.display(),
+            total
+        ));
+    }
+    Ok(lines)
+}
+
+async fn score(store: &dyn Store, config: &Config, target: &Target) -> Result<i64, Error> {
+    let key = Key::new("target", target.key());
+    let item = store.get(&config.karma_table_name, &key).await?;
+    Ok(item
+        .and_then(|item| item.get("karma").and_then(Value::as_i64))
+        .unwrap_or(0))
+}
+
+// A user's karma, for their App Home.
+pub(crate) async fn user_score(
+    store: &dyn Store,
+    config: &Config,
+    user_id: &str,
+) -> Result<i64, Error> {
+    score(store, config, &Target::User(user_id.to_string())).await
+}
+
+#[async_trait]
+impl Command for Karma {
+    fn name(&self) -> &'static str {
+        "karma"
+    }
+
+    fn description(&self) -> &'static str {
+        "Say @someone++ or thing-- to change karma, or !karma to check it."
+    }
+
+    fn usage(&self) -> String {
+        "!karma [@user | thing]".to_string()
+    }
+
+    fn matches(&self, ctx: &CommandContext) -> bool {
+        let invoked = ctx
+            .invocation
+            .as_ref()
+            .is_some_and(|invocation| invocation.name == self.name());
+        // Slash commands can only check karma, not change it.
+        let changes = ctx
+            .message()
+            .is_some_and(|message| !parse_changes(&message.text).is_empty());
+        invoked || changes
+    }
+
+    async fn execute(&self, ctx: &CommandContext<'_>) -> Result<(), Error> {
+        if let Some(invocation) = ctx.invocation.as_ref().filter(|i| i.name == self.name()) {
+            let target = match (invocation.user_mentions.first(), ctx.args().first()) {
+                (Some(user_id), _) => Target::User(user_id.clone()),
+                (None, Some(thing)) => Target::Thing(thing.to_lowercase()),
+                (None, None) => Target::User(ctx.user_id.to_string()),
+            };
+            let score = score(ctx.store, ctx.config, &target).await?;
+            ctx.reply(&format!("{} has {} karma.", target.display(), score))
+                .await?;
+            return Ok(());
+        }
+
+        let message = match ctx.message() {
+            Some(message) => message,
+            None => return Ok(()),
+        };
+        let changes = parse_changes(&message.text);
+        let lines = apply_changes(
+            ctx.store,
+            ctx.config,
+            ctx.user_id,
+            &message.ts,
+            &changes,
+            crate::unix_now(),
+        )
+        .await?;
+        ctx.reply(&lines.join("\n")).await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::store::memory::MemoryStore;
+
+    fn user(user_id: &str, delta: i64) -> Change {
+        Change {
+            target: Target::User(user_id.to_string()),
+            delta,
+        }
+    }
+
+    fn thing(thing: &str, delta: i64) -> Change {
+        Change {
+            target: Target::Thing(thing.to_string()),
+            delta,
+        }
+    }
+
+    fn config() -> Config {
+        Config::from_vars(|name| match name {
+            "SLACK_API_BOT_TOKEN" => Some("xoxb-test".to_string()),
+            "SLACK_SIGNING_SECRET" => Some("secret".to_string()),
+            "BUNS_TABLE_NAME" => Some("buns".to_string()),
+            "KARMA_TABLE_NAME" => Some("karma".to_string()),
+            "KARMA_HISTORY_TABLE_NAME" => Some("karma-history".to_string()),
+            _ => None,
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn parses_changes() {
+        assert_eq!(
+            parse_changes("<@U1>++ thanks, and <@U2|sam> -- for the bug. Bagels++!"),
+            vec![user("U1", 1), user("U2", -1), thing("bagels", 1)]
+        );
+        assert_eq!(parse_changes("<@U1>++ <@U1>++"), vec![user("U1", 1)]);
+        assert!(parse_changes("run `i++` with --verbose, x +++ y").is_empty());
+        assert!(parse_changes("!buns top 5").is_empty());
+        assert!(parse_changes("I love C++").is_empty());
+        assert!(parse_changes("compile with g++ -O2").is_empty());
+        assert_eq!(parse_changes("C++ and go++"), vec![thing("go", 1)]);
+    }
+
+    #[tokio::test]
+    async fn applies_changes_but_not_to_yourself() {
+        let store = MemoryStore::default();
+        let config = config();
+        let changes = vec![user("U2", 1), user("U1", 1), thing("bagels", -1)];
+
+        let lines = apply_changes(&store, &config, "U1", "1645903860.9", &changes, 1645903861)
+            .await
+            .unwrap();
+
+        assert_eq!(
+            lines,
+            vec![
+                "<@U2> now has 1 karma.",
+                "Nice try, but you can't change your own karma.",
+                "bagels now has -1 karma.",
+            ]
+        );
+        assert_eq!(
+            score(&store, &config, &Target::User("U2".into()))
+                .await
+                .unwrap(),
+            1
+        );
+        let history = store.query("karma-history", &Query::Scan).await.unwrap();
+        assert_eq!(history.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn counts_a_redelivered_message_once() {
+        let store = MemoryStore::default();
+        let config = config();
+        let changes = vec![user("U2", 1), thing("bagels", 1)];
+
+        for _ in 0..2 {
+            let lines = apply_changes(&store, &config, "U1", "1645903860.9", &changes, 1645903861)
+                .await
+                .unwrap();
+            assert_eq!(
+                lines,
+                vec!["<@U2> now has 1 karma.", "bagels now has 1 karma."]
+            );
+        }
+        let history = store.query("karma-history", &Query::Scan).await.unwrap();
+        assert_eq!(history.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn rate_limits_givers() {
+        let store = MemoryStore::default();
+        let config = config();
+        let now: i64 = 1645903860;
+        for n in 0..MAX_CHANGES_PER_WINDOW as i64 {
+            let ts = format!("{}.0", now - WINDOW_SECS + n);
+            let changes = vec![thing(&format!("thing{}", n), 1)];
+            apply_changes(&store, &config, "U1", &ts, &changes, now)
+                .await
+                .unwrap();
+        }
+
+        let lines = apply_changes(&store, &config, "U1", "1645903860.0", &[user("U2", 1)], now)
+            .await
+            .unwrap();
+        assert_eq!(
+            lines,
+            vec!["You've handed out a lot of karma lately, give it a rest for a bit."]
+        );
+
+        // Changes older than the window no longer count.
+        let later = now + 60;
+        let lines = apply_changes(
+            &store,
+            &config,
+            "U1",
+            "1645903920.0",
+            &[user("U2", 1)],
+            later,
+        )
+        .await
+        .unwrap();
+        assert_eq!(lines, vec!["<@U2> now has 1 karma."]);
+    }
+}
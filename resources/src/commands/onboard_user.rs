@@ -1,29 +1,25 @@
-use crate::commands::{chat_post_message, conversations_open};
-use serde_json::json;
+use crate::slack::api::{ChatPostMessageRequest, ConversationsOpenRequest};
+use crate::slack::client::{SlackClient, SlackError};
+use crate::slack::events::User;
 
-// TODO: write docs
-pub async fn run(username: &str, first_name: &str) {
-    let text: String = format!(":codedevils_flash: Hey welcome to CodeDevils {} :codedevils_flash: I am DevilBot and I don't know much yet, but here's what I do know: \
+// Sends a welcome DM to a user who just joined the workspace.
+pub async fn run(slack: &SlackClient, user: &User) -> Result<(), SlackError> {
+    let first_name: &str = user.profile.first_name.as_deref().unwrap_or("");
+    let text: String = format!(":codedevils_flash: Hey welcome to CodeDevils {} :codedevils_flash: I am DevilBot, say `!help` to see everything I can do. Here's what you should know first: \
         This Slack workspace serves as the main communication platform for all things CodeDevils :partywizard: All our announcements can be found in the <#C30L07P18> channel. \
         This includes all meetings and meeting recordings! I'd like you to go to the <#CMGU8033K> channel and introduce yourself. After that, come on over to\
         <#C2N5P84BD>. Most of my creators are there all day.", &first_name);
 
-    // After acceptance testing change this to the username
-    let conversation_with_user_and_devilbot_payload = json!({ "users": username });
-    let conversations_open_response =
-        conversations_open::open_conversation(&conversation_with_user_and_devilbot_payload).await;
-    log::info!(
-        "Conversations Open Response {:?}",
-        conversations_open_response
-    );
+    // Open a DM between the new user and DevilBot to send the welcome in.
+    let conversation = slack
+        .conversations_open(&ConversationsOpenRequest {
+            users: user.id.clone(),
+        })
+        .await?;
+    log::info!("Channel Id {:?}", conversation.channel.id);
 
-    let channel_id: &str = conversations_open_response["channel"]["id"]
-        .as_str()
-        .unwrap_or("invalid_id");
-    log::info!("Channel Id {:?}", channel_id);
-    let onboard_message_request_json = json!({
-        "channel": channel_id,
-        "text": &text,
-    });
-    chat_post_message::post_message(&onboard_message_request_json).await
+    slack
+        .chat_post_message(&ChatPostMessageRequest::new(conversation.channel.id, text))
+        .await?;
+    Ok(())
 }
@@ -0,0 +1,221 @@
+use std::collections::HashMap;
+use std::fmt;
+
+// Turns message text addressed to DevilBot into a structured invocation.
+// A message is addressed to DevilBot when it starts with the command prefix
+// or with a mention of the bot:
+//
+//   !buns top 5
+//   @DevilBot buns top 5
+//   !remind "team meeting" --at=5pm <#C30L07P18>
+//
+// Slack encodes mentions as <@U123|name> and <#C123|name>, so those are
+// pulled out into their own lists instead of the positional arguments.
+pub const COMMAND_PREFIX: char = '!';
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Invocation {
+    // Lowercased command name, without the prefix.
+    pub name: String,
+    pub args: Vec<String>,
+    // --flag parses to ("flag", None) and --key=value to ("key", Some("value")).
+    pub flags: HashMap<String, Option<String>>,
+    pub user_mentions: Vec<String>,
+    pub channel_mentions: Vec<String>,
+}
+
+impl Invocation {
+    pub fn has_flag(&self, flag: &str) -> bool {
+        self.flags.contains_key(flag)
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum ParseError {
+    MissingCommand,
+    UnterminatedQuote,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseError::MissingCommand => write!(f, "you didn't say which command to run"),
+            ParseError::UnterminatedQuote => {
+                write!(f, "a quoted argument is missing its closing quote")
+            }
+        }
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+// Returns Ok(None) when the text isn't addressed to DevilBot at all. The
+// prefix only counts when a letter or digit follows it, so "! wow" and "!!!"
+// are just chatter, and MissingCommand only comes from a bare mention.
+// `bot_user_id` is DevilBot's own user ID, used to recognize mentions.
+pub fn parse(text: &str, bot_user_id: Option<&str>) -> Result<Option<Invocation>, ParseError> {
+    let text = text.trim();
+    let rest: &str = if let Some(rest) = text.strip_prefix(COMMAND_PREFIX) {
+        if !rest.starts_with(char::is_alphanumeric) {
+            return Ok(None);
+        }
+        rest
+    } else if let Some(rest) = bot_user_id.and_then(|id| strip_mention(text, id)) {
+        rest
+    } else {
+        return Ok(None);
+    };
+
+    let mut tokens = tokenize(rest)?.into_iter();
+    let name = match tokens.next() {
+        Some(Token::Word(name)) => name.to_lowercase(),
+        _ => return Err(ParseError::MissingCommand),
+    };
+
+    let mut invocation = Invocation {
+        name,
+        ..Invocation::default()
+    };
+    for token in tokens {
+        match token {
+            Token::Quoted(arg) => invocation.args.push(arg),
+            Token::Word(word) => {
+                if let Some(user_id) = parse_mention(&word, "<@") {
+                    invocation.user_mentions.push(user_id);
+                } else if let Some(channel_id) = parse_mention(&word, "<#") {
+                    invocation.channel_mentions.push(channel_id);
+                } else if let Some(flag) = word.strip_prefix("--").filter(|flag| !flag.is_empty()) {
+                    let (key, value) = match flag.split_once('=') {
+                        Some((key, value)) => (key, Some(value.to_string())),
+                        None => (flag, None),
+                    };
+                    invocation.flags.insert(key.to_lowercase(), value);
+                } else {
+                    invocation.args.push(word);
+                }
+            }
+        }
+    }
+    Ok(Some(invocation))
+}
+
+// Pulls the ID out of a Slack mention such as <@U123> or <#C123|general>.
+pub fn parse_mention(word: &str, opener: &str) -> Option<String> {
+    let inner = word.strip_prefix(opener)?.strip_suffix('>')?;
+    let id = inner.split('|').next().unwrap_or(inner);
+    if id.is_empty() {
+        None
+    } else {
+        Some(id.to_string())
+    }
+}
+
+fn strip_mention<'a>(text: &'a str, bot_user_id: &str) -> Option<&'a str> {
+    let rest = text.strip_prefix("<@")?.strip_prefix(bot_user_id)?;
+    let end = rest.find('>')?;
+    // Either a bare mention or one carrying a display name after a '|'.
+    if end == 0 || rest.starts_with('|') {
+        Some(&rest[end + 1..])
+    } else {
+        None
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum Token {
+    Word(String),
+    Quoted(String),
+}
+
+// Splits on whitespace while keeping "quoted arguments" together. Slack
+// clients often turn straight quotes into curly ones, so both count.
+fn tokenize(text: &str) -> Result<Vec<Token>, ParseError> {
+    let mut tokens = Vec::new();
+    let mut chars = text.chars().peekable();
+    while let Some(&c) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+        } else if is_quote(c) {
+            chars.next();
+            let mut quoted = String::new();
+            loop {
+                match chars.next() {
+                    Some(c) if is_quote(c) => break,
+                    Some(c) => quoted.push(c),
+                    None => return Err(ParseError::UnterminatedQuote),
+                }
+            }
+            tokens.push(Token::Quoted(quoted));
+        } else {
+            let mut word = String::new();
+            while let Some(&c) = chars.peek() {
+                if c.is_whitespace() {
+                    break;
+                }
+                word.push(c);
+                chars.next();
+            }
+            tokens.push(Token::Word(word));
+        }
+    }
+    Ok(tokens)
+}
+
+fn is_quote(c: char) -> bool {
+    matches!(c, '"' | '\u{201C}' | '\u{201D}')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BOT: Option<&str> = Some("U0BOT");
+
+    #[test]
+    fn ignores_messages_not_addressed_to_the_bot() {
+        assert_eq!(parse("I love buns", BOT), Ok(None));
+        assert_eq!(parse("<@U999> buns", BOT), Ok(None));
+        assert_eq!(parse("<@U0BOTX> buns", BOT), Ok(None));
+        assert_eq!(parse("<@U0BOT> buns", None), Ok(None));
+        assert_eq!(parse("!", BOT), Ok(None));
+        assert_eq!(parse("! wow", BOT), Ok(None));
+        assert_eq!(parse("!!!", BOT), Ok(None));
+    }
+
+    #[test]
+    fn parses_prefix_command_with_args() {
+        let invocation = parse("!Buns top 5", BOT).unwrap().unwrap();
+        assert_eq!(invocation.name, "buns");
+        assert_eq!(invocation.args, vec!["top", "5"]);
+    }
+
+    #[test]
+    fn parses_mention_command() {
+        let invocation = parse("<@U0BOT> ping", BOT).unwrap().unwrap();
+        assert_eq!(invocation.name, "ping");
+        let invocation = parse("<@U0BOT|devilbot>  ping", BOT).unwrap().unwrap();
+        assert_eq!(invocation.name, "ping");
+    }
+
+    #[test]
+    fn parses_quotes_flags_and_mentions() {
+        let invocation = parse(
+            "!remind \u{201C}team meeting\u{201D} <@U123|jt> --at=5pm <#C30L07P18|announcements> --loud",
+            BOT,
+        )
+        .unwrap()
+        .unwrap();
+        assert_eq!(invocation.name, "remind");
+        assert_eq!(invocation.args, vec!["team meeting"]);
+        assert_eq!(invocation.user_mentions, vec!["U123"]);
+        assert_eq!(invocation.channel_mentions, vec!["C30L07P18"]);
+        assert_eq!(invocation.flags.get("at"), Some(&Some("5pm".to_string())));
+        assert!(invocation.has_flag("loud"));
+    }
+
+    #[test]
+    fn reports_usage_errors() {
+        assert_eq!(parse("<@U0BOT>", BOT), Err(ParseError::MissingCommand));
+        assert_eq!(parse("!say \"hi", BOT), Err(ParseError::UnterminatedQuote));
+    }
+}
@@ -1,15 +1,23 @@
-use crate::commands::chat_post_message;
-use serde_json::json;
-
-// This function parses the text event from the slack event subscription
-// if the text contains any form of "ping", it will respond with "pong".
-// This can be used as an example command when creating new commands for
-// the Slack bot.
-pub async fn run(channel: &str) {
-    let text = "pong";
-    let chat_request_json = json!({
-        "channel": channel,
-        "text": text,
-    });
-    let status = chat_post_message::post_message(&chat_request_json).await;
+use crate::commands::{Command, CommandContext};
+use async_trait::async_trait;
+use lambda_http::Error;
+
+// Responds to "ping" with "pong". This can be used as an example
+// command when creating new commands for the Slack bot.
+pub struct Ping;
+
+#[async_trait]
+impl Command for Ping {
+    fn name(&self) -> &'static str {
+        "ping"
+    }
+
+    fn description(&self) -> &'static str {
+        "Checks that DevilBot is awake by replying \"pong\"."
+    }
+
+    async fn execute(&self, ctx: &CommandContext<'_>) -> Result<(), Error> {
+        ctx.say("pong").await?;
+        Ok(())
+    }
 }
@@ -0,0 +1,61 @@
+use crate::store::{Item, Key, Store, Value};
+use lambda_http::Error;
+use std::sync::Arc;
+
+// Slack retries an event if we don't answer within 3 seconds, and again
+// after 1 and 5 minutes if we keep failing. Every delivery of the same event
+// shares an event_id, so we record each one we see and skip the repeats.
+// Read more here: https://api.slack.com/apis/connections/events-api#retries
+
+// How long an event_id is remembered. Slack gives up retrying well before this.
+pub const EVENT_ID_TTL_SECS: i64 = 60 * 60 * 24;
+
+// Remembers seen event IDs in a table with `event_id` as its partition key
+// and `expires_at` as its TTL attribute. Backed by the in-memory store,
+// duplicates are only caught within one Lambda instance.
+pub struct EventStore {
+    store: Arc<dyn Store>,
+    table_name: String,
+}
+
+impl EventStore {
+    pub fn new(store: Arc<dyn Store>, table_name: String) -> Self {
+        EventStore { store, table_name }
+    }
+
+    // Marks the event as being processed. Returns false if it was already
+    // claimed by an earlier delivery, in which case it must not run again.
+    pub async fn claim(&self, event_id: &str) -> Result<bool, Error> {
+        let expires_at: i64 = crate::unix_now() + EVENT_ID_TTL_SECS;
+        let mut attributes = Item::new();
+        attributes.insert("expires_at".to_string(), Value::N(expires_at));
+        let key = Key::new("event_id", event_id);
+        self.store
+            .put_if_absent(&self.table_name, &key, attributes)
+            .await
+    }
+
+    // Forgets the event, so the next delivery of it is processed again. For
+    // when handling failed before any of its work was done.
+    pub async fn release(&self, event_id: &str) -> Result<(), Error> {
+        let key = Key::new("event_id", event_id);
+        self.store.delete(&self.table_name, &key).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::store::memory::MemoryStore;
+
+    #[tokio::test]
+    async fn claims_each_event_once() {
+        let store = EventStore::new(Arc::new(MemoryStore::default()), "events".to_string());
+        assert!(store.claim("Ev0356A5S917").await.unwrap());
+        assert!(!store.claim("Ev0356A5S917").await.unwrap());
+        assert!(store.claim("Ev0356A5S918").await.unwrap());
+
+        store.release("Ev0356A5S917").await.unwrap();
+        assert!(store.claim("Ev0356A5S917").await.unwrap());
+    }
+}
@@ -0,0 +1,105 @@
+// Routes interaction payloads (button clicks, menu picks, modal submissions
+// and shortcuts) to the handler registered for their action_id or
+// callback_id. Commands register handlers for the buttons and modals they
+// create in Command::register_interactions.
+
+use crate::config::Config;
+use crate::slack::api::ResponseMessage;
+use crate::slack::client::SlackClient;
+use crate::slack::interactions::{Interaction, View};
+use crate::store::Store;
+use crate::AppState;
+use async_trait::async_trait;
+use lambda_http::Error;
+use std::collections::HashMap;
+
+// Everything a handler gets to know about the interaction.
+pub struct InteractionContext<'a> {
+    pub slack: &'a SlackClient,
+    pub config: &'a Config,
+    pub store: &'a dyn Store,
+    pub interaction: &'a Interaction,
+    pub user_id: &'a str,
+}
+
+impl<'a> InteractionContext<'a> {
+    pub fn new(state: &'a AppState, interaction: &'a Interaction, user_id: &'a str) -> Self {
+        InteractionContext {
+            slack: &state.slack,
+            config: &state.config,
+            store: state.store.as_ref(),
+            interaction,
+            user_id,
+        }
+    }
+
+    // Answers through the interaction's response_url, e.g. to replace the
+    // message a button was clicked on. Only interactions on messages have one.
+    pub async fn respond(&self, message: &ResponseMessage) -> Result<(), Error> {
+        let response_url: &str = match self.interaction {
+            Interaction::BlockActions(actions) => actions.response_url.as_deref(),
+            Interaction::MessageAction(shortcut) => Some(shortcut.response_url.as_str()),
+            _ => None,
+        }
+        .ok_or("this interaction has no response_url")?;
+        self.slack.respond(response_url, message).await?;
+        Ok(())
+    }
+}
+
+#[async_trait]
+pub trait InteractionHandler: Send + Sync {
+    // Checks a modal submission before it is queued, since only the
+    // immediate response can keep the modal open. Returns error messages by
+    // block_id, shown under those inputs; empty means the submission is fine.
+    fn validate(&self, _view: &View) -> HashMap<String, String> {
+        HashMap::new()
+    }
+
+    async fn handle(&self, ctx: &InteractionContext<'_>) -> Result<(), Error>;
+}
+
+#[derive(Default)]
+pub struct InteractionRouter {
+    handlers: HashMap<&'static str, Box<dyn InteractionHandler>>,
+}
+
+impl InteractionRouter {
+    // Registers the handler for an action_id or callback_id. IDs are global
+    // to the app, so prefix them with the command's name, e.g. "buns_refresh_top".
+    pub fn register(&mut self, id: &'static str, handler: impl InteractionHandler + 'static) {
+        if self.handlers.insert(id, Box::new(handler)).is_some() {
+            log::info!("Replaced the interaction handler for {}", id);
+        }
+    }
+
+    pub fn find(&self, id: &str) -> Option<&dyn InteractionHandler> {
+        self.handlers.get(id).map(|handler| handler.as_ref())
+    }
+
+    // Errors to show in a submitted modal, if its handler finds any.
+    pub fn validate(&self, interaction: &Interaction) -> HashMap<String, String> {
+        match interaction {
+            Interaction::ViewSubmission(submission) => self
+                .find(&submission.view.callback_id)
+                .map(|handler| handler.validate(&submission.view))
+                .unwrap_or_default(),
+            _ => HashMap::new(),
+        }
+    }
+
+    // Runs the handler for every ID in the interaction. Interactions nobody
+    // handles are logged and otherwise ignored, like unhandled events.
+    pub async fn dispatch(&self, ctx: &InteractionContext<'_>) -> Result<(), Error> {
+        for id in ctx.interaction.route_ids() {
+            match self.find(id) {
+                Some(handler) => {
+                    log::info!("Running interaction handler {}", id);
+                    handler.handle(ctx).await?;
+                }
+                None => log::info!("No interaction handler for {}", id),
+            }
+        }
+        Ok(())
+    }
+}
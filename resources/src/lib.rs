@@ -0,0 +1,360 @@
+// DevilBot's request handling, shared by the binaries in src/: `bootstrap`
+// (src/main.rs) answers Slack's requests on AWS Lambda and `worker` runs the
+// commands they queue up (see queue.rs). `local_server` does both in one
+// process for development, and `replay` runs fixture events through them.
+
+pub mod aws;
+pub mod channels;
+pub mod commands;
+pub mod config;
+pub mod dedup;
+pub mod interactions;
+pub mod queue;
+pub mod slack;
+pub mod store;
+#[cfg(any(test, feature = "testing"))]
+pub mod testing;
+pub mod worker;
+
+use commands::{CommandContext, CommandRegistry};
+use config::Config;
+use dedup::EventStore;
+use interactions::{InteractionContext, InteractionRouter};
+use lambda_http::http::header::CONTENT_TYPE;
+use lambda_http::http::{HeaderMap, StatusCode};
+use lambda_http::{Body, Error, IntoResponse, Request, Response};
+use queue::{Job, Queue};
+use serde_json::{json, Value};
+use slack::api::ResponseMessage;
+use slack::client::SlackClient;
+use slack::events::{Envelope, Event, EventCallback, MessageEvent, UrlVerification};
+use slack::interactions::Interaction;
+use slack::slash_commands::SlashCommand;
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+use store::memory::MemoryStore;
+use store::Store;
+
+const SLACK_RETRY_NUM_HEADER: &str = "x-slack-retry-num";
+const SLACK_RETRY_REASON_HEADER: &str = "x-slack-retry-reason";
+const SLACK_NO_RETRY_HEADER: &str = "x-slack-no-retry";
+const FORM_CONTENT_TYPE: &str = "application/x-www-form-urlencoded";
+
+// Everything the handler needs that should outlive a single invocation.
+pub struct AppState {
+    pub config: Config,
+    pub store: Arc<dyn Store>,
+    pub events: EventStore,
+    pub queue: Arc<dyn Queue>,
+    pub commands: CommandRegistry,
+    pub interactions: InteractionRouter,
+    pub slack: SlackClient,
+}
+
+impl AppState {
+    // Builds everything from the config around the given store and queue.
+    // Create it once per process so clients and connections are reused.
+    pub fn new(config: Config, store: Arc<dyn Store>, queue: Arc<dyn Queue>) -> Self {
+        // Without a table, duplicates are only caught within one Lambda instance.
+        let events = match &config.events_table_name {
+            Some(table_name) => EventStore::new(store.clone(), table_name.clone()),
+            None => {
+                log::info!("No events table configured, remembering events in memory");
+                EventStore::new(Arc::new(MemoryStore::default()), "events".to_string())
+            }
+        };
+        // Which channels each command may respond in. See channels.rs for the format.
+        let mut commands = CommandRegistry::default();
+        commands.set_channel_policies(config.channel_policies.clone());
+        let mut interactions = InteractionRouter::default();
+        for command in commands.commands() {
+            command.register_interactions(&mut interactions);
+        }
+        commands::app_home::register_interactions(&mut interactions);
+
+        let mut slack = SlackClient::new(config.slack_bot_token.clone());
+        if let Some(base_url) = &config.slack_api_base_url {
+            slack = slack.with_base_url(base_url.clone());
+        }
+
+        AppState {
+            config,
+            store,
+            events,
+            queue,
+            commands,
+            interactions,
+            slack,
+        }
+    }
+}
+
+// This is the main event handler in the AWS Lambda. It parses the
+// requests that were sent to the static endpoint behind our AWS
+// API Gateway.
+pub async fn handler(request: Request, state: &AppState) -> Result<Response<Body>, Error> {
+    let (parts, body) = request.into_parts();
+
+    // Anyone who finds the API Gateway URL can POST to it, so make sure the
+    // request was really signed by Slack before looking at the body.
+    let signing_secret: &str = &state.config.slack_signing_secret;
+    if let Err(err) = slack::signature::verify(signing_secret, &parts.headers, &body, unix_now()) {
+        log::info!("Rejecting request with bad Slack signature: {}", err);
+        return Ok(Response::builder()
+            .status(StatusCode::UNAUTHORIZED)
+            .body(Body::Empty)?);
+    }
+
+    // Slash commands and interactions are sent as forms, everything from the
+    // Events API as JSON.
+    if is_form(&parts.headers) {
+        if Interaction::is_payload(&body) {
+            return handle_interaction(&body, state).await;
+        }
+        return handle_slash_command(&body, state).await;
+    }
+
+    // Tell the caller what was wrong with the body instead of failing the
+    // whole invocation.
+    let envelope: Envelope = match serde_json::from_slice(&body) {
+        Ok(envelope) => envelope,
+        Err(err) => {
+            log::info!("Could not parse request body: {}", err);
+            return Ok(Response::builder()
+                .status(StatusCode::BAD_REQUEST)
+                .body(Body::from(format!("Invalid Slack event payload: {}", err)))?);
+        }
+    };
+    log::info!("{:?}", envelope);
+
+    match envelope {
+        Envelope::UrlVerification(verification) => {
+            Ok(intercept_challenge_request(&verification).into_response())
+        }
+        Envelope::EventCallback(callback) => {
+            // Slack redelivers events it thinks we missed. Anything we have
+            // already seen is acknowledged straight away so it isn't retried again.
+            if !state.events.claim(&callback.event_id).await? {
+                log::info!(
+                    "Skipping duplicate event {} (retry {:?}, reason {:?})",
+                    callback.event_id,
+                    parts.headers.get(SLACK_RETRY_NUM_HEADER),
+                    parts.headers.get(SLACK_RETRY_REASON_HEADER)
+                );
+                return Ok(Response::builder()
+                    .status(StatusCode::OK)
+                    .header(SLACK_NO_RETRY_HEADER, "1")
+                    .body(Body::Empty)?);
+            }
+            // The commands run in the worker, so Slack gets its answer well
+            // within its 3 second timeout.
+            let job = Job::EventCallback {
+                body: String::from_utf8_lossy(&body).into_owned(),
+            };
+            if let Err(err) = state.queue.enqueue(&job).await {
+                // Let Slack's retry through, since this delivery never ran.
+                state.events.release(&callback.event_id).await?;
+                return Err(err);
+            }
+            empty_response(StatusCode::OK)
+        }
+        Envelope::AppRateLimited(rate_limited) => {
+            log::info!(
+                "Slack stopped sending events for minute {} because we are rate limited",
+                rate_limited.minute_rate_limited
+            );
+            empty_response(StatusCode::OK)
+        }
+    }
+}
+
+fn is_form(headers: &HeaderMap) -> bool {
+    headers
+        .get(CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.starts_with(FORM_CONTENT_TYPE))
+}
+
+// Slash commands are answered straight away when there is nothing to run,
+// like an unknown command, so only the user sees the mistake. Anything else
+// is queued for the worker, which replies through the command's response_url.
+async fn handle_slash_command(body: &Body, state: &AppState) -> Result<Response<Body>, Error> {
+    let command =
+        match SlashCommand::from_form(body) {
+            Ok(command) => command,
+            Err(err) => {
+                log::info!("Could not parse slash command: {}", err);
+                return Ok(Response::builder().status(StatusCode::BAD_REQUEST).body(
+                    Body::from(format!("Invalid slash command payload: {}", err)),
+                )?);
+            }
+        };
+    log::info!("{:?}", command);
+
+    let refusal: Option<String> = match state.commands.parse_slash_command(&command) {
+        Ok(invocation) => {
+            let ctx = CommandContext::for_slash_command(state, &command, invocation);
+            state.commands.refusal(&ctx)
+        }
+        Err(err) => Some(format!("Sorry, {}.", err)),
+    };
+    if let Some(text) = refusal {
+        return Ok(json!(ResponseMessage::ephemeral(text)).into_response());
+    }
+
+    let job = Job::SlashCommand {
+        body: String::from_utf8_lossy(body).into_owned(),
+    };
+    state.queue.enqueue(&job).await?;
+    empty_response(StatusCode::OK)
+}
+
+// Interactions are queued for the worker like events. Slack waits for the
+// response to a modal submission to decide whether to close the modal, so
+// those are validated here first.
+async fn handle_interaction(body: &Body, state: &AppState) -> Result<Response<Body>, Error> {
+    let interaction = match Interaction::from_form(body) {
+        Ok(interaction) => interaction,
+        Err(err) => {
+            log::info!("Could not parse interaction payload: {}", err);
+            return Ok(Response::builder()
+                .status(StatusCode::BAD_REQUEST)
+                .body(Body::from(format!("Invalid interaction payload: {}", err)))?);
+        }
+    };
+    log::info!("{:?}", interaction);
+
+    let errors = state.interactions.validate(&interaction);
+    if !errors.is_empty() {
+        return Ok(json!({ "response_action": "errors", "errors": errors }).into_response());
+    }
+
+    let job = Job::Interaction {
+        body: String::from_utf8_lossy(body).into_owned(),
+    };
+    state.queue.enqueue(&job).await?;
+    empty_response(StatusCode::OK)
+}
+
+// When you create a Slack event subscription, your endpoint needs
+// to respond to a challenge request with the challenge ID for
+// the subscription to be successfully created.
+// Read more here: https://api.slack.com/events/url_verification
+fn intercept_challenge_request(verification: &UrlVerification) -> Value {
+    log::info!(
+        "token: {}\nchallenge: {}\ntype: url_verification",
+        verification.token,
+        verification.challenge
+    );
+    json!({ "challenge": verification.challenge })
+}
+
+fn empty_response(status: StatusCode) -> Result<Response<Body>, Error> {
+    Ok(Response::builder().status(status).body(Body::Empty)?)
+}
+
+// This function looks at the event delivered in an event callback
+// and runs whichever command it triggers, if any. It runs in the worker,
+// and an error means the event should be retried.
+pub(crate) async fn intercept_command(
+    callback: &EventCallback,
+    state: &AppState,
+) -> Result<(), Error> {
+    let message: &MessageEvent = match &callback.event {
+        Event::TeamJoin(team_join) => {
+            commands::onboard_user::run(&state.slack, &team_join.user).await?;
+            return Ok(());
+        }
+        Event::AppHomeOpened(app_home) if app_home.tab == "home" => {
+            commands::app_home::publish(state, &app_home.user).await?;
+            return Ok(());
+        }
+        Event::Message(message) => message,
+        event => {
+            log::info!("Unhandled event type {:?}", event);
+            return Ok(());
+        }
+    };
+    log::info!(
+        "text: {}, channel: {}, user_id: {:?}, is_bot {}",
+        message.text,
+        message.channel,
+        message.user,
+        message.is_bot()
+    );
+
+    // Prevent responding to bots
+    if message.is_bot() {
+        log::info!("This is a bot");
+        return Ok(());
+    }
+    let user_id: &str = match &message.user {
+        Some(user_id) => user_id,
+        None => {
+            log::info!("Message has no user");
+            return Ok(());
+        }
+    };
+    // Messages like "!buns top 5" or "@DevilBot buns top 5" are parsed
+    // into an invocation; anything else is left for the commands' own matchers.
+    let bot_user_id: Option<&str> = callback
+        .authorizations
+        .first()
+        .map(|authorization| authorization.user_id.as_str());
+    let invocation = match commands::parser::parse(&message.text, bot_user_id) {
+        Ok(invocation) => invocation,
+        Err(err) => {
+            let is_direct_message =
+                channels::is_direct_message(&message.channel, message.channel_type.as_deref());
+            if state
+                .commands
+                .default_policy()
+                .allows(&message.channel, is_direct_message)
+            {
+                let text = format!("Sorry, {}.", err);
+                if let Err(err) = commands::reply(&state.slack, message, &text).await {
+                    log::info!("Could not report parse error: {}", err);
+                }
+            }
+            return Ok(());
+        }
+    };
+    // New commands are registered in CommandRegistry::default().
+    let ctx = CommandContext::new(state, message, user_id, invocation);
+    state.commands.dispatch(&ctx).await
+}
+
+// Runs a slash command the handler queued. Like intercept_command, this
+// runs in the worker.
+pub(crate) async fn run_slash_command(
+    command: &SlashCommand,
+    state: &AppState,
+) -> Result<(), Error> {
+    let invocation = state.commands.parse_slash_command(command)?;
+    let ctx = CommandContext::for_slash_command(state, command, invocation);
+    state.commands.dispatch(&ctx).await
+}
+
+// Runs the handlers for an interaction the handler queued, in the worker.
+pub(crate) async fn run_interaction(
+    interaction: &Interaction,
+    state: &AppState,
+) -> Result<(), Error> {
+    let user_id: &str = match interaction.user() {
+        Some(user) => &user.id,
+        None => {
+            log::info!("Unhandled interaction {:?}", interaction);
+            return Ok(());
+        }
+    };
+    let ctx = InteractionContext::new(state, interaction, user_id);
+    state.interactions.dispatch(&ctx).await
+}
+
+// Current unix time in seconds, used to reject replayed Slack requests.
+pub fn unix_now() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs() as i64)
+        .unwrap_or_default()
+}
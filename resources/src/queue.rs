@@ -0,0 +1,29 @@
+// Slack gives us 3 seconds to answer each request, which DynamoDB plus a
+// couple of Web API calls can easily blow through. So the HTTP handler only
+// checks the request and puts a Job on a queue; the worker (worker.rs) takes
+// jobs off the queue and does the actual work. In production the queue is
+// SQS (aws/sqs.rs), locally it is an in-process channel.
+
+pub mod local;
+
+use async_trait::async_trait;
+use lambda_http::Error;
+use serde_derive::{Deserialize, Serialize};
+
+// Work the worker should do. Jobs are sent through SQS as JSON.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Job {
+    // An Events API callback, as the raw JSON body Slack sent.
+    EventCallback { body: String },
+    // A slash command, as the raw form body Slack sent. Its replies go to
+    // the command's response_url.
+    SlashCommand { body: String },
+    // A button click, modal submission or shortcut, as the raw form body.
+    Interaction { body: String },
+}
+
+#[async_trait]
+pub trait Queue: Send + Sync {
+    async fn enqueue(&self, job: &Job) -> Result<(), Error>;
+}
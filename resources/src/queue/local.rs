@@ -0,0 +1,27 @@
+use crate::queue::{Job, Queue};
+use async_trait::async_trait;
+use lambda_http::Error;
+use tokio::sync::mpsc::{self, UnboundedReceiver, UnboundedSender};
+
+// Hands jobs to a worker in the same process, see worker::run_local. Jobs
+// still waiting are lost if the process exits, so this is only for local
+// runs and tests.
+pub struct LocalQueue {
+    sender: UnboundedSender<Job>,
+}
+
+// The queue and the receiving end the worker reads jobs from.
+pub fn channel() -> (LocalQueue, UnboundedReceiver<Job>) {
+    let (sender, receiver) = mpsc::unbounded_channel();
+    (LocalQueue { sender }, receiver)
+}
+
+#[async_trait]
+impl Queue for LocalQueue {
+    async fn enqueue(&self, job: &Job) -> Result<(), Error> {
+        self.sender
+            .send(job.clone())
+            .map_err(|_| "the local worker has stopped")?;
+        Ok(())
+    }
+}
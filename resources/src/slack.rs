@@ -0,0 +1,8 @@
+pub mod api;
+pub mod blocks;
+pub mod client;
+pub mod events;
+pub mod interactions;
+pub mod rate_limit;
+pub mod signature;
+pub mod slash_commands;
@@ -0,0 +1,149 @@
+use crate::slack::blocks::Block;
+use serde_derive::{Deserialize, Serialize};
+
+// Request and response bodies for the Slack Web API methods DevilBot calls.
+// Responses only model the fields we use; the "ok" and "error" fields every
+// response carries are checked by SlackClient before these are deserialized.
+// Read more here: https://api.slack.com/methods
+
+// https://api.slack.com/methods/chat.postMessage
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ChatPostMessageRequest {
+    pub channel: String,
+    pub text: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub thread_ts: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub blocks: Option<Vec<Block>>,
+}
+
+impl ChatPostMessageRequest {
+    pub fn new(channel: impl Into<String>, text: impl Into<String>) -> Self {
+        ChatPostMessageRequest {
+            channel: channel.into(),
+            text: text.into(),
+            ..ChatPostMessageRequest::default()
+        }
+    }
+
+    pub fn in_thread(mut self, thread_ts: impl Into<String>) -> Self {
+        self.thread_ts = Some(thread_ts.into());
+        self
+    }
+
+    // Blocks replace the text in the message itself; the text is still used
+    // for notifications and by clients that can't show blocks.
+    pub fn with_blocks(mut self, blocks: Vec<Block>) -> Self {
+        self.blocks = Some(blocks);
+        self
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ChatPostMessageResponse {
+    pub channel: String,
+    pub ts: String,
+}
+
+// https://api.slack.com/methods/conversations.open
+#[derive(Debug, Clone, Serialize)]
+pub struct ConversationsOpenRequest {
+    // Comma separated list of user IDs.
+    pub users: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ConversationsOpenResponse {
+    pub channel: Conversation,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Conversation {
+    pub id: String,
+}
+
+// https://api.slack.com/methods/reactions.add
+#[derive(Debug, Clone, Serialize)]
+pub struct ReactionRequest {
+    pub channel: String,
+    // The ts of the message to react to.
+    pub timestamp: String,
+    // Emoji name without colons, e.g. "heart".
+    pub name: String,
+}
+
+// https://api.slack.com/methods/views.publish
+#[derive(Debug, Clone, Serialize)]
+pub struct ViewsPublishRequest {
+    pub user_id: String,
+    pub view: HomeView,
+}
+
+// A user's App Home tab. https://api.slack.com/surfaces/app-home
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "type", rename = "home")]
+pub struct HomeView {
+    pub blocks: Vec<Block>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub callback_id: Option<String>,
+}
+
+// A message sent in answer to a slash command, either as the response to
+// Slack's request or later to its response_url. Not a Web API method, but
+// it looks like one. https://api.slack.com/interactivity/handling#message_responses
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ResponseMessage {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub response_type: Option<ResponseType>,
+    pub text: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub blocks: Option<Vec<Block>>,
+    // Set to replace the message an interaction happened on.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub replace_original: Option<bool>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ResponseType {
+    // Everyone in the channel sees the response, and the command that caused it.
+    InChannel,
+    // Only the user who ran the command sees it. Slack's default.
+    Ephemeral,
+}
+
+impl ResponseMessage {
+    pub fn in_channel(text: impl Into<String>) -> Self {
+        ResponseMessage {
+            response_type: Some(ResponseType::InChannel),
+            text: text.into(),
+            ..ResponseMessage::default()
+        }
+    }
+
+    pub fn ephemeral(text: impl Into<String>) -> Self {
+        ResponseMessage {
+            response_type: Some(ResponseType::Ephemeral),
+            text: text.into(),
+            ..ResponseMessage::default()
+        }
+    }
+
+    // Replaces the message the interaction happened on, keeping who can see it.
+    pub fn replacing_original(text: impl Into<String>) -> Self {
+        ResponseMessage {
+            text: text.into(),
+            replace_original: Some(true),
+            ..ResponseMessage::default()
+        }
+    }
+
+    pub fn with_blocks(mut self, blocks: Vec<Block>) -> Self {
+        self.blocks = Some(blocks);
+        self
+    }
+}
+
+// For methods that return nothing but "ok".
+#[derive(Debug, Clone, Deserialize)]
+pub struct EmptyResponse {}
@@ -0,0 +1,573 @@
+use serde_derive::Serialize;
+use std::fmt;
+
+// Typed Block Kit blocks for rich messages, modals and App Home. They
+// serialize to the JSON Slack expects, and `validate` checks them against
+// Slack's size limits before they are sent, since Slack rejects the whole
+// message with a vague `invalid_blocks` otherwise.
+// Read more here: https://api.slack.com/reference/block-kit/blocks
+//
+//   vec![
+//       Header::new("Buns leaderboard").into(),
+//       Section::new(Text::mrkdwn("1. <@U123> 5 :buns:")).into(),
+//       Actions::new(vec![Button::new("Refresh", "buns_refresh_top").into()]).into(),
+//   ]
+
+// Messages can hold up to 50 blocks, modals and App Home up to 100.
+pub const MAX_MESSAGE_BLOCKS: usize = 50;
+pub const MAX_VIEW_BLOCKS: usize = 100;
+
+const MAX_BLOCK_ID: usize = 255;
+const MAX_ACTION_ID: usize = 255;
+const MAX_URL: usize = 3000;
+
+// https://api.slack.com/reference/block-kit/composition-objects#text
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Text {
+    PlainText {
+        text: String,
+        // Turns :emoji: codes into emoji.
+        #[serde(skip_serializing_if = "Option::is_none")]
+        emoji: Option<bool>,
+    },
+    Mrkdwn {
+        text: String,
+    },
+}
+
+impl Text {
+    pub fn plain(text: impl Into<String>) -> Self {
+        Text::PlainText {
+            text: text.into(),
+            emoji: Some(true),
+        }
+    }
+
+    pub fn mrkdwn(text: impl Into<String>) -> Self {
+        Text::Mrkdwn { text: text.into() }
+    }
+
+    pub fn text(&self) -> &str {
+        match self {
+            Text::PlainText { text, .. } | Text::Mrkdwn { text } => text,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Block {
+    Header(Header),
+    Section(Section),
+    Context(Context),
+    Divider(Divider),
+    Actions(Actions),
+    Image(ImageBlock),
+}
+
+// https://api.slack.com/reference/block-kit/blocks#header
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Header {
+    // Always plain text.
+    pub text: Text,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub block_id: Option<String>,
+}
+
+impl Header {
+    pub fn new(text: impl Into<String>) -> Self {
+        Header {
+            text: Text::plain(text),
+            block_id: None,
+        }
+    }
+}
+
+// https://api.slack.com/reference/block-kit/blocks#section
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct Section {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub text: Option<Text>,
+    // Shown in two columns.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub fields: Vec<Text>,
+    // Shown to the right of the text.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub accessory: Option<Element>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub block_id: Option<String>,
+}
+
+impl Section {
+    pub fn new(text: Text) -> Self {
+        Section {
+            text: Some(text),
+            ..Section::default()
+        }
+    }
+
+    pub fn fields(fields: Vec<Text>) -> Self {
+        Section {
+            fields,
+            ..Section::default()
+        }
+    }
+
+    pub fn accessory(mut self, element: impl Into<Element>) -> Self {
+        self.accessory = Some(element.into());
+        self
+    }
+
+    pub fn block_id(mut self, block_id: impl Into<String>) -> Self {
+        self.block_id = Some(block_id.into());
+        self
+    }
+}
+
+// Small, grey text and images. https://api.slack.com/reference/block-kit/blocks#context
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Context {
+    pub elements: Vec<ContextElement>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub block_id: Option<String>,
+}
+
+impl Context {
+    pub fn new(elements: Vec<ContextElement>) -> Self {
+        Context {
+            elements,
+            block_id: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(untagged)]
+pub enum ContextElement {
+    Text(Text),
+    Image(ImageElement),
+}
+
+// https://api.slack.com/reference/block-kit/blocks#divider
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct Divider {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub block_id: Option<String>,
+}
+
+// A row of buttons and menus. https://api.slack.com/reference/block-kit/blocks#actions
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Actions {
+    pub elements: Vec<Element>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub block_id: Option<String>,
+}
+
+impl Actions {
+    pub fn new(elements: Vec<Element>) -> Self {
+        Actions {
+            elements,
+            block_id: None,
+        }
+    }
+
+    pub fn block_id(mut self, block_id: impl Into<String>) -> Self {
+        self.block_id = Some(block_id.into());
+        self
+    }
+}
+
+// https://api.slack.com/reference/block-kit/blocks#image
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ImageBlock {
+    pub image_url: String,
+    pub alt_text: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub title: Option<Text>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub block_id: Option<String>,
+}
+
+impl ImageBlock {
+    pub fn new(image_url: impl Into<String>, alt_text: impl Into<String>) -> Self {
+        ImageBlock {
+            image_url: image_url.into(),
+            alt_text: alt_text.into(),
+            title: None,
+            block_id: None,
+        }
+    }
+}
+
+// Interactive elements and images, for actions blocks and section accessories.
+// https://api.slack.com/reference/block-kit/block-elements
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(untagged)]
+pub enum Element {
+    Button(Button),
+    StaticSelect(StaticSelect),
+    Image(ImageElement),
+}
+
+// Clicks arrive as block_actions interactions with the button's action_id
+// and value, see interactions.rs.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "type", rename = "button")]
+pub struct Button {
+    pub text: Text,
+    pub action_id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub value: Option<String>,
+    // Opens the URL in the user's browser as well.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub style: Option<ButtonStyle>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ButtonStyle {
+    Primary,
+    Danger,
+}
+
+impl Button {
+    pub fn new(text: impl Into<String>, action_id: impl Into<String>) -> Self {
+        Button {
+            text: Text::plain(text),
+            action_id: action_id.into(),
+            value: None,
+            url: None,
+            style: None,
+        }
+    }
+
+    pub fn value(mut self, value: impl Into<String>) -> Self {
+        self.value = Some(value.into());
+        self
+    }
+
+    pub fn url(mut self, url: impl Into<String>) -> Self {
+        self.url = Some(url.into());
+        self
+    }
+
+    pub fn style(mut self, style: ButtonStyle) -> Self {
+        self.style = Some(style);
+        self
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "type", rename = "static_select")]
+pub struct StaticSelect {
+    pub placeholder: Text,
+    pub action_id: String,
+    pub options: Vec<SelectOption>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub initial_option: Option<SelectOption>,
+}
+
+impl StaticSelect {
+    pub fn new(
+        placeholder: impl Into<String>,
+        action_id: impl Into<String>,
+        options: Vec<SelectOption>,
+    ) -> Self {
+        StaticSelect {
+            placeholder: Text::plain(placeholder),
+            action_id: action_id.into(),
+            options,
+            initial_option: None,
+        }
+    }
+
+    pub fn initial_option(mut self, option: SelectOption) -> Self {
+        self.initial_option = Some(option);
+        self
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SelectOption {
+    pub text: Text,
+    pub value: String,
+}
+
+impl SelectOption {
+    pub fn new(text: impl Into<String>, value: impl Into<String>) -> Self {
+        SelectOption {
+            text: Text::plain(text),
+            value: value.into(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "type", rename = "image")]
+pub struct ImageElement {
+    pub image_url: String,
+    pub alt_text: String,
+}
+
+impl ImageElement {
+    pub fn new(image_url: impl Into<String>, alt_text: impl Into<String>) -> Self {
+        ImageElement {
+            image_url: image_url.into(),
+            alt_text: alt_text.into(),
+        }
+    }
+}
+
+macro_rules! impl_from {
+    ($($from:ty => $to:ident::$variant:ident),* $(,)?) => {
+        $(impl From<$from> for $to {
+            fn from(value: $from) -> Self {
+                $to::$variant(value)
+            }
+        })*
+    };
+}
+
+impl_from! {
+    Header => Block::Header,
+    Section => Block::Section,
+    Context => Block::Context,
+    Divider => Block::Divider,
+    Actions => Block::Actions,
+    ImageBlock => Block::Image,
+    Button => Element::Button,
+    StaticSelect => Element::StaticSelect,
+    ImageElement => Element::Image,
+    Text => ContextElement::Text,
+    ImageElement => ContextElement::Image,
+}
+
+// Why Slack would reject some blocks, e.g.
+// "block 2: section text is longer than 3000 characters".
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BlockError(pub String);
+
+impl fmt::Display for BlockError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl std::error::Error for BlockError {}
+
+// Checks blocks against Slack's limits. `max_blocks` is MAX_MESSAGE_BLOCKS
+// or MAX_VIEW_BLOCKS. https://api.slack.com/reference/block-kit/blocks
+pub fn validate(blocks: &[Block], max_blocks: usize) -> Result<(), BlockError> {
+    if blocks.len() > max_blocks {
+        return Err(BlockError(format!(
+            "{} blocks is more than the {} allowed",
+            blocks.len(),
+            max_blocks
+        )));
+    }
+    for (index, block) in blocks.iter().enumerate() {
+        block
+            .validate()
+            .map_err(|err| BlockError(format!("block {}: {}", index, err)))?;
+    }
+    Ok(())
+}
+
+fn check_len(what: &str, value: &str, max: usize) -> Result<(), BlockError> {
+    let len: usize = value.chars().count();
+    if len > max {
+        return Err(BlockError(format!(
+            "{} is longer than {} characters",
+            what, max
+        )));
+    }
+    Ok(())
+}
+
+fn check_count(what: &str, count: usize, max: usize) -> Result<(), BlockError> {
+    if count > max {
+        return Err(BlockError(format!("more than {} {}", max, what)));
+    }
+    Ok(())
+}
+
+fn check_block_id(block_id: &Option<String>) -> Result<(), BlockError> {
+    match block_id {
+        Some(block_id) => check_len("block_id", block_id, MAX_BLOCK_ID),
+        None => Ok(()),
+    }
+}
+
+impl Block {
+    fn validate(&self) -> Result<(), BlockError> {
+        match self {
+            Block::Header(header) => {
+                check_len("header text", header.text.text(), 150)?;
+                check_block_id(&header.block_id)
+            }
+            Block::Section(section) => {
+                if section.text.is_none() && section.fields.is_empty() {
+                    return Err(BlockError("section needs text or fields".to_string()));
+                }
+                if let Some(text) = &section.text {
+                    check_len("section text", text.text(), 3000)?;
+                }
+                check_count("section fields", section.fields.len(), 10)?;
+                for field in &section.fields {
+                    check_len("section field", field.text(), 2000)?;
+                }
+                if let Some(accessory) = &section.accessory {
+                    accessory.validate()?;
+                }
+                check_block_id(&section.block_id)
+            }
+            Block::Context(context) => {
+                check_count("context elements", context.elements.len(), 10)?;
+                for element in &context.elements {
+                    match element {
+                        ContextElement::Text(text) => check_len("context text", text.text(), 3000)?,
+                        ContextElement::Image(image) => image.validate()?,
+                    }
+                }
+                check_block_id(&context.block_id)
+            }
+            Block::Divider(divider) => check_block_id(&divider.block_id),
+            Block::Actions(actions) => {
+                check_count("actions elements", actions.elements.len(), 25)?;
+                for element in &actions.elements {
+                    element.validate()?;
+                }
+                check_block_id(&actions.block_id)
+            }
+            Block::Image(image) => {
+                check_len("image_url", &image.image_url, MAX_URL)?;
+                check_len("image alt_text", &image.alt_text, 2000)?;
+                if let Some(title) = &image.title {
+                    check_len("image title", title.text(), 2000)?;
+                }
+                check_block_id(&image.block_id)
+            }
+        }
+    }
+}
+
+impl Element {
+    fn validate(&self) -> Result<(), BlockError> {
+        match self {
+            Element::Button(button) => {
+                check_len("button text", button.text.text(), 75)?;
+                check_len("action_id", &button.action_id, MAX_ACTION_ID)?;
+                if let Some(value) = &button.value {
+                    check_len("button value", value, 2000)?;
+                }
+                if let Some(url) = &button.url {
+                    check_len("button url", url, MAX_URL)?;
+                }
+                Ok(())
+            }
+            Element::StaticSelect(select) => {
+                check_len("select placeholder", select.placeholder.text(), 150)?;
+                check_len("action_id", &select.action_id, MAX_ACTION_ID)?;
+                check_count("select options", select.options.len(), 100)?;
+                for option in select.options.iter().chain(&select.initial_option) {
+                    check_len("option text", option.text.text(), 75)?;
+                    check_len("option value", &option.value, 150)?;
+                }
+                Ok(())
+            }
+            Element::Image(image) => image.validate(),
+        }
+    }
+}
+
+impl ImageElement {
+    fn validate(&self) -> Result<(), BlockError> {
+        check_len("image_url", &self.image_url, MAX_URL)?;
+        check_len("image alt_text", &self.alt_text, 2000)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn serializes_to_block_kit_json() {
+        let blocks: Vec<Block> = vec![
+            Header::new("Buns").into(),
+            Section::new(Text::mrkdwn("*bold*"))
+                .accessory(StaticSelect::new(
+                    "Pick one",
+                    "pick",
+                    vec![SelectOption::new("One", "1")],
+                ))
+                .into(),
+            Context::new(vec![
+                ImageElement::new("https://example.com/a.png", "a").into(),
+                Text::mrkdwn("small").into(),
+            ])
+            .into(),
+            Divider::default().into(),
+            Actions::new(vec![Button::new("Go", "go")
+                .value("1")
+                .style(ButtonStyle::Primary)
+                .into()])
+            .into(),
+        ];
+
+        assert_eq!(
+            serde_json::to_value(&blocks).unwrap(),
+            json!([
+                {"type": "header", "text": {"type": "plain_text", "text": "Buns", "emoji": true}},
+                {
+                    "type": "section",
+                    "text": {"type": "mrkdwn", "text": "*bold*"},
+                    "accessory": {
+                        "type": "static_select",
+                        "placeholder": {"type": "plain_text", "text": "Pick one", "emoji": true},
+                        "action_id": "pick",
+                        "options": [{"text": {"type": "plain_text", "text": "One", "emoji": true}, "value": "1"}]
+                    }
+                },
+                {
+                    "type": "context",
+                    "elements": [
+                        {"type": "image", "image_url": "https://example.com/a.png", "alt_text": "a"},
+                        {"type": "mrkdwn", "text": "small"}
+                    ]
+                },
+                {"type": "divider"},
+                {
+                    "type": "actions",
+                    "elements": [{
+                        "type": "button",
+                        "text": {"type": "plain_text", "text": "Go", "emoji": true},
+                        "action_id": "go",
+                        "value": "1",
+                        "style": "primary"
+                    }]
+                }
+            ])
+        );
+        assert_eq!(validate(&blocks, MAX_MESSAGE_BLOCKS), Ok(()));
+    }
+
+    #[test]
+    fn rejects_blocks_over_slacks_limits() {
+        let long_text: Block = Section::new(Text::mrkdwn("x".repeat(3001))).into();
+        let too_many: Vec<Block> = vec![Divider::default().into(); MAX_MESSAGE_BLOCKS + 1];
+
+        assert_eq!(
+            validate(&[Divider::default().into(), long_text], MAX_MESSAGE_BLOCKS),
+            Err(BlockError(
+                "block 1: section text is longer than 3000 characters".to_string()
+            ))
+        );
+        assert!(validate(&too_many, MAX_MESSAGE_BLOCKS).is_err());
+        assert!(validate(&too_many, MAX_VIEW_BLOCKS).is_ok());
+    }
+}
@@ -0,0 +1,439 @@
+use crate::slack::api::{
+    ChatPostMessageRequest, ChatPostMessageResponse, ConversationsOpenRequest,
+    ConversationsOpenResponse, EmptyResponse, ReactionRequest, ResponseMessage,
+    ViewsPublishRequest,
+};
+use crate::slack::blocks::{self, Block, BlockError, MAX_MESSAGE_BLOCKS, MAX_VIEW_BLOCKS};
+use crate::slack::rate_limit::{RateLimiter, RetryPolicy};
+use reqwest::header::RETRY_AFTER;
+use reqwest::StatusCode;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::Value;
+use std::fmt;
+use std::sync::Arc;
+use std::time::Duration;
+
+pub const SLACK_API_BASE_URL: &str = "https://slack.com/api";
+
+// Used when Slack sends a 429 without a usable Retry-After header.
+const DEFAULT_RETRY_AFTER: Duration = Duration::from_secs(1);
+
+// How long a single request may take, from connecting to reading the whole
+// response. Slack answers in well under a second, so a call that hangs past
+// this is better retried or failed than left to run into the Lambda timeout.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+// Methods that do something new on every call. When one of their requests
+// may have reached Slack we can't tell whether it worked, so it is only sent
+// again when it never left or Slack turned it away with a 429.
+const NOT_IDEMPOTENT: &[&str] = &["chat.postMessage"];
+
+// A client for the Slack Web API. It holds the bot token and a single
+// reqwest::Client, so every call shares one connection pool. Calls are
+// throttled per method to Slack's rate limit tiers and retried when Slack
+// asks us to slow down or has a transient failure. Clone it freely, clones
+// share the pool and the rate limits too.
+#[derive(Clone)]
+pub struct SlackClient {
+    http: reqwest::Client,
+    token: String,
+    base_url: String,
+    rate_limiter: Arc<RateLimiter>,
+    retry_policy: RetryPolicy,
+}
+
+#[derive(Debug)]
+pub enum SlackError {
+    // The request never got a response.
+    Http(reqwest::Error),
+    // Slack answered with something other than 200 OK.
+    Status {
+        method: String,
+        status: StatusCode,
+    },
+    // Slack kept answering 429 Too Many Requests after every retry.
+    RateLimited {
+        method: String,
+        retry_after: Duration,
+    },
+    // Slack answered 200 OK with {"ok": false, "error": ...}.
+    Api {
+        method: String,
+        error: String,
+    },
+    // The response body wasn't what we expected.
+    Decode {
+        method: String,
+        source: serde_json::Error,
+    },
+    // The blocks break Slack's limits, so the request was never sent.
+    Blocks {
+        method: String,
+        source: BlockError,
+    },
+}
+
+impl fmt::Display for SlackError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SlackError::Http(err) => write!(f, "request to Slack failed: {}", err),
+            SlackError::Status { method, status } => {
+                write!(f, "{} responded with HTTP {}", method, status)
+            }
+            SlackError::RateLimited {
+                method,
+                retry_after,
+            } => write!(
+                f,
+                "{} is rate limited for another {:?}",
+                method, retry_after
+            ),
+            SlackError::Api { method, error } => write!(f, "{} failed: {}", method, error),
+            SlackError::Decode { method, source } => {
+                write!(f, "could not decode {} response: {}", method, source)
+            }
+            SlackError::Blocks { method, source } => {
+                write!(f, "{} was not sent: {}", method, source)
+            }
+        }
+    }
+}
+
+impl std::error::Error for SlackError {}
+
+impl From<reqwest::Error> for SlackError {
+    fn from(err: reqwest::Error) -> Self {
+        SlackError::Http(err)
+    }
+}
+
+impl SlackError {
+    // Failures that may succeed if the same call is simply made again later.
+    // Only a failed connection is sure not to have reached Slack, so that is
+    // all we retry for methods that aren't idempotent.
+    fn is_transient(&self, idempotent: bool) -> bool {
+        match self {
+            SlackError::Http(err) if err.is_connect() => true,
+            _ if !idempotent => false,
+            SlackError::Http(err) => err.is_timeout() || err.is_request(),
+            SlackError::Status { status, .. } => status.is_server_error(),
+            _ => false,
+        }
+    }
+
+    // Failures that will happen again however often the call is repeated,
+    // like {"ok": false, "error": "user_not_found"}, so a job that hits one
+    // gives up instead of being retried.
+    pub fn is_permanent(&self) -> bool {
+        match self {
+            SlackError::Api { .. } | SlackError::Decode { .. } | SlackError::Blocks { .. } => true,
+            SlackError::Status { status, .. } => status.is_client_error(),
+            SlackError::Http(_) | SlackError::RateLimited { .. } => false,
+        }
+    }
+}
+
+impl SlackClient {
+    pub fn new(token: impl Into<String>) -> Self {
+        SlackClient {
+            http: reqwest::Client::builder()
+                .timeout(REQUEST_TIMEOUT)
+                .build()
+                .expect("the HTTP client settings are valid"),
+            token: token.into(),
+            base_url: SLACK_API_BASE_URL.to_string(),
+            rate_limiter: Arc::new(RateLimiter::default()),
+            retry_policy: RetryPolicy::default(),
+        }
+    }
+
+    pub fn with_retry_policy(mut self, retry_policy: RetryPolicy) -> Self {
+        self.retry_policy = retry_policy;
+        self
+    }
+
+    // Sends requests somewhere other than https://slack.com/api, e.g. a
+    // mock server in tests.
+    pub fn with_base_url(mut self, base_url: impl Into<String>) -> Self {
+        self.base_url = base_url.into().trim_end_matches('/').to_string();
+        self
+    }
+
+    // Calls any Web API method with a JSON body. Prefer the typed methods
+    // below where one exists.
+    pub async fn call<Req, Resp>(&self, method: &str, request: &Req) -> Result<Resp, SlackError>
+    where
+        Req: Serialize + ?Sized,
+        Resp: DeserializeOwned,
+    {
+        let idempotent: bool = !NOT_IDEMPOTENT.contains(&method);
+        let mut attempt: u32 = 0;
+        loop {
+            self.rate_limiter.acquire(method).await;
+            let err = match self.call_once(method, request).await {
+                Ok(response) => return Ok(response),
+                Err(err) => err,
+            };
+            if attempt >= self.retry_policy.max_retries {
+                return Err(err);
+            }
+            match &err {
+                SlackError::RateLimited { retry_after, .. } => {
+                    log::info!("{} was rate limited, retrying in {:?}", method, retry_after);
+                    self.rate_limiter.pause(method, *retry_after);
+                }
+                err if err.is_transient(idempotent) => {
+                    let backoff = self.retry_policy.backoff(attempt);
+                    log::info!("{}, retrying in {:?}", err, backoff);
+                    tokio::time::sleep(backoff).await;
+                }
+                _ => return Err(err),
+            }
+            attempt += 1;
+        }
+    }
+
+    async fn call_once<Req, Resp>(&self, method: &str, request: &Req) -> Result<Resp, SlackError>
+    where
+        Req: Serialize + ?Sized,
+        Resp: DeserializeOwned,
+    {
+        let response = self
+            .http
+            .post(format!("{}/{}", self.base_url, method))
+            .bearer_auth(&self.token)
+            .json(request)
+            .send()
+            .await?;
+
+        let status = response.status();
+        if status == StatusCode::TOO_MANY_REQUESTS {
+            let retry_after = response
+                .headers()
+                .get(RETRY_AFTER)
+                .and_then(|value| value.to_str().ok())
+                .and_then(|value| value.trim().parse().ok())
+                .map(Duration::from_secs)
+                .unwrap_or(DEFAULT_RETRY_AFTER);
+            return Err(SlackError::RateLimited {
+                method: method.to_string(),
+                retry_after,
+            });
+        }
+        if status != StatusCode::OK {
+            return Err(SlackError::Status {
+                method: method.to_string(),
+                status,
+            });
+        }
+
+        let decode_error = |source| SlackError::Decode {
+            method: method.to_string(),
+            source,
+        };
+        let body: Value = serde_json::from_slice(&response.bytes().await?).map_err(decode_error)?;
+        if body["ok"] != Value::Bool(true) {
+            return Err(SlackError::Api {
+                method: method.to_string(),
+                error: body["error"]
+                    .as_str()
+                    .unwrap_or("unknown_error")
+                    .to_string(),
+            });
+        }
+        if let Some(warning) = body["warning"].as_str() {
+            log::info!("{} warning: {}", method, warning);
+        }
+        serde_json::from_value(body).map_err(decode_error)
+    }
+
+    pub async fn chat_post_message(
+        &self,
+        request: &ChatPostMessageRequest,
+    ) -> Result<ChatPostMessageResponse, SlackError> {
+        check_blocks(
+            "chat.postMessage",
+            request.blocks.as_deref(),
+            MAX_MESSAGE_BLOCKS,
+        )?;
+        self.call("chat.postMessage", request).await
+    }
+
+    pub async fn conversations_open(
+        &self,
+        request: &ConversationsOpenRequest,
+    ) -> Result<ConversationsOpenResponse, SlackError> {
+        self.call("conversations.open", request).await
+    }
+
+    pub async fn reactions_add(&self, request: &ReactionRequest) -> Result<(), SlackError> {
+        self.call::<_, EmptyResponse>("reactions.add", request)
+            .await
+            .map(|_| ())
+    }
+
+    // Replaces a user's App Home tab.
+    pub async fn views_publish(&self, request: &ViewsPublishRequest) -> Result<(), SlackError> {
+        check_blocks("views.publish", Some(&request.view.blocks), MAX_VIEW_BLOCKS)?;
+        self.call::<_, EmptyResponse>("views.publish", request)
+            .await
+            .map(|_| ())
+    }
+
+    // Posts a delayed reply to a slash command's response_url. These aren't
+    // Web API calls: no token is needed, Slack answers with plain text, and
+    // each URL can only be used a few times, so nothing is retried.
+    pub async fn respond(
+        &self,
+        response_url: &str,
+        message: &ResponseMessage,
+    ) -> Result<(), SlackError> {
+        check_blocks(
+            "response_url",
+            message.blocks.as_deref(),
+            MAX_MESSAGE_BLOCKS,
+        )?;
+        let response = self.http.post(response_url).json(message).send().await?;
+        let status = response.status();
+        if status != StatusCode::OK {
+            return Err(SlackError::Status {
+                method: "response_url".to_string(),
+                status,
+            });
+        }
+        Ok(())
+    }
+}
+
+fn check_blocks(
+    method: &str,
+    blocks: Option<&[Block]>,
+    max_blocks: usize,
+) -> Result<(), SlackError> {
+    match blocks {
+        Some(blocks) => blocks::validate(blocks, max_blocks).map_err(|source| SlackError::Blocks {
+            method: method.to_string(),
+            source,
+        }),
+        None => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hyper::service::{make_service_fn, service_fn};
+    use hyper::{Body, Request, Response, Server};
+    use serde_json::json;
+    use std::convert::Infallible;
+    use std::net::SocketAddr;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    // Serves the scripted (status, body) responses in order, repeating the
+    // last one, and counts how many requests it received. 429s ask the
+    // client to retry straight away.
+    async fn mock_slack(responses: Vec<(u16, Value)>) -> (String, Arc<AtomicUsize>) {
+        let responses = Arc::new(responses);
+        let requests = Arc::new(AtomicUsize::new(0));
+        let counter = requests.clone();
+        let make_service = make_service_fn(move |_| {
+            let responses = responses.clone();
+            let counter = counter.clone();
+            async move {
+                Ok::<_, Infallible>(service_fn(move |_request: Request<Body>| {
+                    let index = counter.fetch_add(1, Ordering::SeqCst);
+                    let (status, body) = &responses[index.min(responses.len() - 1)];
+                    let response = Response::builder()
+                        .status(*status)
+                        .header("retry-after", "0")
+                        .body(Body::from(body.to_string()));
+                    async move { response }
+                }))
+            }
+        });
+        let server = Server::bind(&SocketAddr::from(([127, 0, 0, 1], 0))).serve(make_service);
+        let base_url = format!("http://{}", server.local_addr());
+        tokio::spawn(server);
+        (base_url, requests)
+    }
+
+    fn client(base_url: &str) -> SlackClient {
+        SlackClient::new("xoxb-test")
+            .with_base_url(base_url)
+            .with_retry_policy(RetryPolicy {
+                max_retries: 2,
+                base_delay: Duration::from_millis(1),
+                max_delay: Duration::from_millis(5),
+            })
+    }
+
+    fn post_message() -> ChatPostMessageRequest {
+        ChatPostMessageRequest::new("C0351GJ62Q0", "pong")
+    }
+
+    #[tokio::test]
+    async fn retries_after_429() {
+        let (base_url, requests) = mock_slack(vec![
+            (429, json!({})),
+            (
+                200,
+                json!({"ok": true, "channel": "C0351GJ62Q0", "ts": "1.2"}),
+            ),
+        ])
+        .await;
+
+        let response = client(&base_url)
+            .chat_post_message(&post_message())
+            .await
+            .unwrap();
+
+        assert_eq!(response.ts, "1.2");
+        assert_eq!(requests.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn retries_server_errors_then_gives_up() {
+        let (base_url, requests) = mock_slack(vec![(503, json!({}))]).await;
+
+        let err = client(&base_url)
+            .conversations_open(&ConversationsOpenRequest {
+                users: "U0DEVILFAN".to_string(),
+            })
+            .await
+            .unwrap_err();
+
+        assert!(matches!(err, SlackError::Status { status, .. } if status.as_u16() == 503));
+        assert_eq!(requests.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn does_not_repost_messages_after_server_errors() {
+        let (base_url, requests) = mock_slack(vec![(503, json!({}))]).await;
+
+        let err = client(&base_url)
+            .chat_post_message(&post_message())
+            .await
+            .unwrap_err();
+
+        assert!(matches!(err, SlackError::Status { status, .. } if status.as_u16() == 503));
+        assert_eq!(requests.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn surfaces_slack_errors_without_retrying() {
+        let (base_url, requests) = mock_slack(vec![(
+            200,
+            json!({"ok": false, "error": "channel_not_found"}),
+        )])
+        .await;
+
+        let err = client(&base_url)
+            .chat_post_message(&post_message())
+            .await
+            .unwrap_err();
+
+        assert!(matches!(err, SlackError::Api { error, .. } if error == "channel_not_found"));
+        assert_eq!(requests.load(Ordering::SeqCst), 1);
+    }
+}
@@ -0,0 +1,325 @@
+use serde_derive::Deserialize;
+
+// Typed versions of the payloads Slack sends to our endpoint through the
+// Events API. Only the fields DevilBot uses are modelled; serde ignores the
+// rest. Read more here: https://api.slack.com/apis/connections/events-api
+
+// The outer envelope of every Events API request, tagged by its "type" field.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Envelope {
+    UrlVerification(UrlVerification),
+    EventCallback(Box<EventCallback>),
+    AppRateLimited(AppRateLimited),
+}
+
+// Sent once when the request URL is configured in the Slack app settings.
+// https://api.slack.com/events/url_verification
+#[derive(Debug, Clone, Deserialize)]
+pub struct UrlVerification {
+    pub token: String,
+    pub challenge: String,
+}
+
+// Wraps every event we are subscribed to.
+#[derive(Debug, Clone, Deserialize)]
+pub struct EventCallback {
+    pub team_id: String,
+    pub api_app_id: String,
+    pub enterprise_id: Option<String>,
+    pub event: Event,
+    pub event_id: String,
+    pub event_time: i64,
+    #[serde(default)]
+    pub authorizations: Vec<Authorization>,
+}
+
+// The installation the event was delivered for. For our bot token,
+// `user_id` is DevilBot's own user ID.
+#[derive(Debug, Clone, Deserialize)]
+pub struct Authorization {
+    pub user_id: String,
+    pub team_id: Option<String>,
+    pub enterprise_id: Option<String>,
+    #[serde(default)]
+    pub is_bot: bool,
+}
+
+// Sent when Slack stops delivering events because we fell too far behind.
+// https://api.slack.com/apis/connections/events-api#rate-limiting
+#[derive(Debug, Clone, Deserialize)]
+pub struct AppRateLimited {
+    pub team_id: String,
+    pub minute_rate_limited: i64,
+    pub api_app_id: String,
+}
+
+// The inner event, tagged by its own "type" field. Event types we have not
+// modelled yet deserialize to `Unsupported` instead of failing the request.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Event {
+    Message(MessageEvent),
+    AppMention(AppMentionEvent),
+    TeamJoin(TeamJoinEvent),
+    ReactionAdded(ReactionEvent),
+    ReactionRemoved(ReactionEvent),
+    MemberJoinedChannel(MemberJoinedChannelEvent),
+    AppHomeOpened(AppHomeOpenedEvent),
+    #[serde(other)]
+    Unsupported,
+}
+
+// https://api.slack.com/events/message
+#[derive(Debug, Clone, Deserialize)]
+pub struct MessageEvent {
+    pub channel: String,
+    pub channel_type: Option<String>,
+    // Missing for bot messages and some subtypes.
+    pub user: Option<String>,
+    #[serde(default)]
+    pub text: String,
+    pub ts: String,
+    pub thread_ts: Option<String>,
+    pub subtype: Option<String>,
+    pub bot_id: Option<String>,
+}
+
+impl MessageEvent {
+    // True when the message was posted by a bot, including DevilBot itself.
+    pub fn is_bot(&self) -> bool {
+        self.bot_id.is_some() || self.subtype.as_deref() == Some("bot_message")
+    }
+}
+
+// https://api.slack.com/events/app_mention
+#[derive(Debug, Clone, Deserialize)]
+pub struct AppMentionEvent {
+    pub channel: String,
+    pub user: String,
+    #[serde(default)]
+    pub text: String,
+    pub ts: String,
+    pub thread_ts: Option<String>,
+}
+
+// https://api.slack.com/events/team_join
+#[derive(Debug, Clone, Deserialize)]
+pub struct TeamJoinEvent {
+    pub user: User,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct User {
+    pub id: String,
+    #[serde(default)]
+    pub profile: Profile,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct Profile {
+    pub first_name: Option<String>,
+    pub real_name: Option<String>,
+    pub display_name: Option<String>,
+}
+
+// https://api.slack.com/events/reaction_added
+#[derive(Debug, Clone, Deserialize)]
+pub struct ReactionEvent {
+    pub user: String,
+    pub reaction: String,
+    pub item: ReactionItem,
+    pub item_user: Option<String>,
+    pub event_ts: String,
+}
+
+// The message a reaction was added to or removed from. Files and file
+// comments don't carry a channel or ts.
+#[derive(Debug, Clone, Deserialize)]
+pub struct ReactionItem {
+    #[serde(rename = "type")]
+    pub kind: String,
+    pub channel: Option<String>,
+    pub ts: Option<String>,
+}
+
+// https://api.slack.com/events/member_joined_channel
+#[derive(Debug, Clone, Deserialize)]
+pub struct MemberJoinedChannelEvent {
+    pub user: String,
+    pub channel: String,
+    pub channel_type: Option<String>,
+    pub inviter: Option<String>,
+}
+
+// Sent every time a user opens DevilBot's App Home, on any of its tabs.
+// https://api.slack.com/events/app_home_opened
+#[derive(Debug, Clone, Deserialize)]
+pub struct AppHomeOpenedEvent {
+    pub user: String,
+    pub channel: String,
+    // "home" or "messages".
+    pub tab: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Wraps an inner event in the event_callback envelope Slack sends it in.
+    fn callback(event: &str) -> String {
+        format!(
+            r#"{{
+                "token": "XXYYZZ",
+                "team_id": "T0DEVILS",
+                "api_app_id": "A0DEVILBOT",
+                "event": {},
+                "type": "event_callback",
+                "event_id": "Ev0DEVIL01",
+                "event_time": 1700000000,
+                "authorizations": [{{"user_id": "U0DEVILBOT", "team_id": "T0DEVILS", "is_bot": true}}]
+            }}"#,
+            event
+        )
+    }
+
+    fn parse_event(event: &str) -> Event {
+        match serde_json::from_str(&callback(event)).unwrap() {
+            Envelope::EventCallback(callback) => {
+                assert_eq!(callback.event_id, "Ev0DEVIL01");
+                assert_eq!(callback.authorizations[0].user_id, "U0DEVILBOT");
+                callback.event
+            }
+            other => panic!("expected an event callback, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn parses_url_verification() {
+        let envelope: Envelope = serde_json::from_str(
+            r#"{
+                "token": "Jhj5dZrVaK7ZwHHjRyZWjbDl",
+                "challenge": "3eZbrw1aBm2rZgRNFdxV2595E9CY3gmdALWMmHkvFXO7tYXAYM8P",
+                "type": "url_verification"
+            }"#,
+        )
+        .unwrap();
+
+        match envelope {
+            Envelope::UrlVerification(verification) => assert_eq!(
+                verification.challenge,
+                "3eZbrw1aBm2rZgRNFdxV2595E9CY3gmdALWMmHkvFXO7tYXAYM8P"
+            ),
+            other => panic!("expected a url verification, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn parses_messages() {
+        let event = parse_event(
+            r#"{
+                "type": "message",
+                "channel": "C0351GJ62Q0",
+                "channel_type": "channel",
+                "user": "U0DEVILFAN",
+                "text": "!ping",
+                "ts": "1700000000.000100",
+                "event_ts": "1700000000.000100"
+            }"#,
+        );
+
+        match event {
+            Event::Message(message) => {
+                assert_eq!(message.user.as_deref(), Some("U0DEVILFAN"));
+                assert_eq!(message.text, "!ping");
+                assert!(!message.is_bot());
+            }
+            other => panic!("expected a message, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn parses_app_mentions() {
+        let event = parse_event(
+            r#"{
+                "type": "app_mention",
+                "channel": "C0351GJ62Q0",
+                "user": "U0DEVILFAN",
+                "text": "<@U0DEVILBOT> ping",
+                "ts": "1700000000.000200",
+                "event_ts": "1700000000.000200"
+            }"#,
+        );
+
+        match event {
+            Event::AppMention(mention) => assert_eq!(mention.text, "<@U0DEVILBOT> ping"),
+            other => panic!("expected an app mention, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn parses_reactions() {
+        let event = parse_event(
+            r#"{
+                "type": "reaction_added",
+                "user": "U0DEVILFAN",
+                "reaction": "buns",
+                "item_user": "U0NEWBIE",
+                "item": {"type": "message", "channel": "C0351GJ62Q0", "ts": "1700000000.000100"},
+                "event_ts": "1700000000.000300"
+            }"#,
+        );
+
+        match event {
+            Event::ReactionAdded(reaction) => {
+                assert_eq!(reaction.reaction, "buns");
+                assert_eq!(reaction.item.kind, "message");
+                assert_eq!(reaction.item.ts.as_deref(), Some("1700000000.000100"));
+            }
+            other => panic!("expected an added reaction, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn parses_team_joins() {
+        let event = parse_event(
+            r#"{
+                "type": "team_join",
+                "user": {
+                    "id": "U0NEWBIE",
+                    "name": "sparky",
+                    "profile": {"first_name": "Sparky", "real_name": "Sparky Devil"}
+                },
+                "event_ts": "1700000000.000400"
+            }"#,
+        );
+
+        match event {
+            Event::TeamJoin(join) => {
+                assert_eq!(join.user.id, "U0NEWBIE");
+                assert_eq!(join.user.profile.first_name.as_deref(), Some("Sparky"));
+            }
+            other => panic!("expected a team join, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn unknown_events_are_unsupported() {
+        let event = parse_event(
+            r#"{"type": "channel_archive", "channel": "C0351GJ62Q0", "user": "U0DEVILFAN"}"#,
+        );
+
+        assert!(matches!(event, Event::Unsupported));
+    }
+
+    #[test]
+    fn rejects_malformed_payloads() {
+        // Not JSON, an unknown envelope type, and a message without a channel.
+        assert!(serde_json::from_str::<Envelope>("token=XXYYZZ").is_err());
+        assert!(serde_json::from_str::<Envelope>(r#"{"type": "block_actions"}"#).is_err());
+        assert!(serde_json::from_str::<Envelope>(&callback(
+            r#"{"type": "message", "user": "U0DEVILFAN", "text": "!ping", "ts": "1700000000.000100"}"#
+        ))
+        .is_err());
+    }
+}
@@ -0,0 +1,274 @@
+use serde_derive::Deserialize;
+use std::collections::HashMap;
+use std::fmt;
+
+// Typed versions of the payloads Slack sends when someone clicks a button,
+// picks from a menu, submits a modal or uses a shortcut. They arrive as a
+// form with a single `payload` field holding the JSON. Only the fields
+// DevilBot uses are modelled.
+// Read more here: https://api.slack.com/interactivity/handling#payloads
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Interaction {
+    BlockActions(BlockActions),
+    ViewSubmission(ViewSubmission),
+    // A global shortcut, started from the shortcuts menu.
+    Shortcut(Shortcut),
+    // A message shortcut, started from a message's "More actions" menu.
+    MessageAction(MessageShortcut),
+    #[serde(other)]
+    Unsupported,
+}
+
+#[derive(Debug)]
+pub enum PayloadError {
+    Form(serde_urlencoded::de::Error),
+    Json(serde_json::Error),
+}
+
+impl fmt::Display for PayloadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PayloadError::Form(err) => write!(f, "not a form with a payload: {}", err),
+            PayloadError::Json(err) => write!(f, "payload is not an interaction: {}", err),
+        }
+    }
+}
+
+impl std::error::Error for PayloadError {}
+
+#[derive(Deserialize)]
+struct Form {
+    payload: String,
+}
+
+impl Interaction {
+    pub fn from_form(body: &[u8]) -> Result<Self, PayloadError> {
+        let form: Form = serde_urlencoded::from_bytes(body).map_err(PayloadError::Form)?;
+        serde_json::from_str(&form.payload).map_err(PayloadError::Json)
+    }
+
+    // Whether a form body is an interaction payload rather than, say, a
+    // slash command.
+    pub fn is_payload(body: &[u8]) -> bool {
+        serde_urlencoded::from_bytes::<Vec<(String, String)>>(body)
+            .is_ok_and(|fields| fields.iter().any(|(name, _)| name == "payload"))
+    }
+
+    // Who clicked, submitted or used the shortcut.
+    pub fn user(&self) -> Option<&UserRef> {
+        match self {
+            Interaction::BlockActions(actions) => Some(&actions.user),
+            Interaction::ViewSubmission(submission) => Some(&submission.user),
+            Interaction::Shortcut(shortcut) => Some(&shortcut.user),
+            Interaction::MessageAction(shortcut) => Some(&shortcut.user),
+            Interaction::Unsupported => None,
+        }
+    }
+
+    // The IDs handlers are registered under: the action_id of each action
+    // in a block_actions payload, otherwise the callback_id.
+    pub fn route_ids(&self) -> Vec<&str> {
+        match self {
+            Interaction::BlockActions(actions) => actions
+                .actions
+                .iter()
+                .map(|action| action.action_id.as_str())
+                .collect(),
+            Interaction::ViewSubmission(submission) => vec![&submission.view.callback_id],
+            Interaction::Shortcut(shortcut) => vec![&shortcut.callback_id],
+            Interaction::MessageAction(shortcut) => vec![&shortcut.callback_id],
+            Interaction::Unsupported => Vec::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct UserRef {
+    pub id: String,
+    pub username: Option<String>,
+    pub team_id: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ChannelRef {
+    pub id: String,
+    pub name: Option<String>,
+}
+
+// The message an interaction happened on.
+#[derive(Debug, Clone, Deserialize)]
+pub struct InteractionMessage {
+    pub ts: String,
+    pub thread_ts: Option<String>,
+    #[serde(default)]
+    pub text: String,
+    pub user: Option<String>,
+}
+
+// https://api.slack.com/reference/interaction-payloads/block-actions
+#[derive(Debug, Clone, Deserialize)]
+pub struct BlockActions {
+    pub user: UserRef,
+    pub trigger_id: String,
+    // Present for actions on messages, not for ones in modals or App Home.
+    pub response_url: Option<String>,
+    pub channel: Option<ChannelRef>,
+    pub message: Option<InteractionMessage>,
+    // Present for actions in modals and App Home.
+    pub view: Option<View>,
+    pub actions: Vec<Action>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Action {
+    pub action_id: String,
+    pub block_id: String,
+    // Set on buttons.
+    pub value: Option<String>,
+    // Set on static select menus.
+    pub selected_option: Option<SelectedOption>,
+    pub action_ts: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct SelectedOption {
+    pub value: String,
+}
+
+// https://api.slack.com/reference/interaction-payloads/views#view_submission
+#[derive(Debug, Clone, Deserialize)]
+pub struct ViewSubmission {
+    pub user: UserRef,
+    pub trigger_id: Option<String>,
+    pub view: View,
+}
+
+// A modal, or a user's App Home.
+#[derive(Debug, Clone, Deserialize)]
+pub struct View {
+    pub id: String,
+    #[serde(default)]
+    pub callback_id: String,
+    // Whatever was passed along when the view was opened.
+    #[serde(default)]
+    pub private_metadata: String,
+    #[serde(default)]
+    pub state: ViewState,
+}
+
+// What the user entered, by block_id and then action_id.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct ViewState {
+    #[serde(default)]
+    pub values: HashMap<String, HashMap<String, InputValue>>,
+}
+
+impl ViewState {
+    pub fn get(&self, block_id: &str, action_id: &str) -> Option<&InputValue> {
+        self.values.get(block_id)?.get(action_id)
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct InputValue {
+    // Set by plain text inputs.
+    pub value: Option<String>,
+    // Set by static select menus.
+    pub selected_option: Option<SelectedOption>,
+    // Set by user select menus.
+    pub selected_user: Option<String>,
+}
+
+// https://api.slack.com/reference/interaction-payloads/shortcuts
+#[derive(Debug, Clone, Deserialize)]
+pub struct Shortcut {
+    pub callback_id: String,
+    pub trigger_id: String,
+    pub user: UserRef,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct MessageShortcut {
+    pub callback_id: String,
+    pub trigger_id: String,
+    pub user: UserRef,
+    pub channel: ChannelRef,
+    pub message: InteractionMessage,
+    pub response_url: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn form(payload: &str) -> Vec<u8> {
+        serde_urlencoded::to_string([("payload", payload)])
+            .unwrap()
+            .into_bytes()
+    }
+
+    #[test]
+    fn parses_block_actions() {
+        let body = form(
+            r#"{
+                "type": "block_actions",
+                "user": {"id": "U0DEVILFAN", "username": "sun", "team_id": "T0DEVILS"},
+                "trigger_id": "12466734323.1395872398",
+                "response_url": "https://hooks.slack.com/actions/T0DEVILS/1/abc",
+                "channel": {"id": "C0351GJ62Q0", "name": "devil-bot-test"},
+                "message": {"ts": "1700000000.000100", "text": "Buns leaderboard"},
+                "actions": [{
+                    "type": "button",
+                    "action_id": "buns_refresh_top",
+                    "block_id": "actions",
+                    "value": "3",
+                    "action_ts": "1700000001.000200"
+                }]
+            }"#,
+        );
+
+        assert!(Interaction::is_payload(&body));
+        let interaction = Interaction::from_form(&body).unwrap();
+
+        assert_eq!(interaction.route_ids(), vec!["buns_refresh_top"]);
+        assert_eq!(interaction.user().unwrap().id, "U0DEVILFAN");
+        match interaction {
+            Interaction::BlockActions(actions) => {
+                assert_eq!(actions.actions[0].value.as_deref(), Some("3"));
+            }
+            other => panic!("expected block actions, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn parses_view_submissions() {
+        let body = form(
+            r#"{
+                "type": "view_submission",
+                "user": {"id": "U0DEVILFAN"},
+                "view": {
+                    "id": "V0123",
+                    "callback_id": "karma_give",
+                    "state": {"values": {"target": {"user": {"type": "users_select", "selected_user": "U0NEWBIE"}}}}
+                }
+            }"#,
+        );
+
+        let interaction = Interaction::from_form(&body).unwrap();
+
+        assert_eq!(interaction.route_ids(), vec!["karma_give"]);
+        match interaction {
+            Interaction::ViewSubmission(submission) => {
+                let input = submission.view.state.get("target", "user").unwrap();
+                assert_eq!(input.selected_user.as_deref(), Some("U0NEWBIE"));
+            }
+            other => panic!("expected a view submission, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn slash_commands_are_not_payloads() {
+        assert!(!Interaction::is_payload(b"command=%2Fdevilbot&text=ping"));
+    }
+}
@@ -0,0 +1,215 @@
+use rand::Rng;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+// Slack limits how often each Web API method may be called, grouped into
+// tiers. We keep a token bucket per method so we stay under those limits
+// instead of finding out through HTTP 429s.
+// DevilBot doesn't call any Tier 1 or Tier 2 (20 per minute) methods.
+// Read more here: https://api.slack.com/docs/rate-limits
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Tier {
+    Tier3,
+    Tier4,
+    // chat.postMessage allows roughly one message per second per channel,
+    // with short bursts.
+    PostMessage,
+}
+
+impl Tier {
+    pub fn for_method(method: &str) -> Tier {
+        match method {
+            "chat.postMessage" => Tier::PostMessage,
+            "conversations.open" | "reactions.add" => Tier::Tier3,
+            "views.publish" | "users.info" => Tier::Tier4,
+            _ => Tier::Tier3,
+        }
+    }
+
+    pub fn per_minute(self) -> u32 {
+        match self {
+            Tier::Tier3 => 50,
+            Tier::Tier4 => 100,
+            Tier::PostMessage => 60,
+        }
+    }
+
+    // How many calls may go out back to back before the rate applies.
+    fn burst(self) -> u32 {
+        match self {
+            Tier::PostMessage => 5,
+            tier => tier.per_minute() / 10,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct TokenBucket {
+    capacity: f64,
+    tokens: f64,
+    refill_per_sec: f64,
+    last_refill: Instant,
+    // Set when Slack tells us to back off with Retry-After.
+    paused_until: Option<Instant>,
+}
+
+impl TokenBucket {
+    pub fn new(capacity: u32, per_minute: u32, now: Instant) -> Self {
+        TokenBucket {
+            capacity: capacity as f64,
+            tokens: capacity as f64,
+            refill_per_sec: per_minute as f64 / 60.0,
+            last_refill: now,
+            paused_until: None,
+        }
+    }
+
+    // Takes a token if one is available. Otherwise returns how long to wait
+    // before trying again.
+    pub fn try_take(&mut self, now: Instant) -> Result<(), Duration> {
+        if let Some(paused_until) = self.paused_until {
+            if now < paused_until {
+                return Err(paused_until - now);
+            }
+            // Nothing refills while paused.
+            self.last_refill = self.last_refill.max(paused_until);
+            self.paused_until = None;
+        }
+
+        let elapsed = now
+            .saturating_duration_since(self.last_refill)
+            .as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.refill_per_sec).min(self.capacity);
+        self.last_refill = now;
+
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            Ok(())
+        } else {
+            let missing = 1.0 - self.tokens;
+            Err(Duration::from_secs_f64(missing / self.refill_per_sec))
+        }
+    }
+
+    pub fn pause_until(&mut self, until: Instant) {
+        self.tokens = 0.0;
+        self.paused_until = Some(self.paused_until.map_or(until, |paused| paused.max(until)));
+    }
+}
+
+// One token bucket per Web API method, created on first use.
+#[derive(Debug, Default)]
+pub struct RateLimiter {
+    buckets: Mutex<HashMap<String, TokenBucket>>,
+}
+
+impl RateLimiter {
+    // Waits until a call to `method` fits within its tier.
+    pub async fn acquire(&self, method: &str) {
+        loop {
+            let wait = {
+                let now = Instant::now();
+                let mut buckets = self.buckets.lock().unwrap();
+                let bucket = buckets.entry(method.to_string()).or_insert_with(|| {
+                    let tier = Tier::for_method(method);
+                    TokenBucket::new(tier.burst(), tier.per_minute(), now)
+                });
+                bucket.try_take(now)
+            };
+            match wait {
+                Ok(()) => return,
+                Err(wait) => {
+                    log::info!("Waiting {:?} to stay under the {} rate limit", wait, method);
+                    tokio::time::sleep(wait).await;
+                }
+            }
+        }
+    }
+
+    // Holds off every call to `method` until Slack's Retry-After has passed.
+    pub fn pause(&self, method: &str, retry_after: Duration) {
+        let now = Instant::now();
+        let mut buckets = self.buckets.lock().unwrap();
+        let bucket = buckets.entry(method.to_string()).or_insert_with(|| {
+            let tier = Tier::for_method(method);
+            TokenBucket::new(tier.burst(), tier.per_minute(), now)
+        });
+        bucket.pause_until(now + retry_after);
+    }
+}
+
+// How often and how patiently to retry calls that failed for reasons that
+// may go away on their own: 429s, 5xx responses and network errors.
+#[derive(Debug, Clone, Copy)]
+pub struct RetryPolicy {
+    pub max_retries: u32,
+    pub base_delay: Duration,
+    pub max_delay: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy {
+            max_retries: 3,
+            base_delay: Duration::from_millis(200),
+            max_delay: Duration::from_secs(5),
+        }
+    }
+}
+
+impl RetryPolicy {
+    // Exponential backoff with full jitter: a random delay between zero and
+    // base_delay * 2^attempt, capped at max_delay.
+    pub fn backoff(&self, attempt: u32) -> Duration {
+        let ceiling = self
+            .base_delay
+            .saturating_mul(2u32.saturating_pow(attempt))
+            .min(self.max_delay);
+        let jittered_ms = rand::thread_rng().gen_range(0..=ceiling.as_millis() as u64);
+        Duration::from_millis(jittered_ms)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bucket_allows_burst_then_refills() {
+        let start = Instant::now();
+        let mut bucket = TokenBucket::new(2, 60, start);
+
+        assert_eq!(bucket.try_take(start), Ok(()));
+        assert_eq!(bucket.try_take(start), Ok(()));
+        assert_eq!(bucket.try_take(start), Err(Duration::from_secs(1)));
+        assert_eq!(bucket.try_take(start + Duration::from_secs(1)), Ok(()));
+    }
+
+    #[test]
+    fn paused_bucket_waits_for_retry_after() {
+        let start = Instant::now();
+        let mut bucket = TokenBucket::new(5, 60, start);
+        bucket.pause_until(start + Duration::from_secs(30));
+
+        assert_eq!(
+            bucket.try_take(start + Duration::from_secs(10)),
+            Err(Duration::from_secs(20))
+        );
+        // Tokens only start refilling once the pause is over.
+        assert_eq!(
+            bucket.try_take(start + Duration::from_millis(30_500)),
+            Err(Duration::from_millis(500))
+        );
+        assert_eq!(bucket.try_take(start + Duration::from_secs(31)), Ok(()));
+    }
+
+    #[test]
+    fn backoff_stays_under_ceiling() {
+        let policy = RetryPolicy::default();
+        for attempt in 0..10 {
+            let ceiling = (policy.base_delay * 2u32.pow(attempt)).min(policy.max_delay);
+            assert!(policy.backoff(attempt) <= ceiling);
+        }
+    }
+}
@@ -0,0 +1,190 @@
+use hmac::{Hmac, Mac};
+use lambda_http::http::HeaderMap;
+use sha2::Sha256;
+use std::fmt;
+
+// Slack signs every request it sends us with the signing secret found on
+// the app's "Basic Information" page. We recompute the signature and compare
+// it before acting on anything in the body.
+// Read more here: https://api.slack.com/authentication/verifying-requests-from-slack
+pub const SIGNATURE_HEADER: &str = "x-slack-signature";
+pub const TIMESTAMP_HEADER: &str = "x-slack-request-timestamp";
+
+// Requests older (or further in the future) than this are treated as replays.
+pub const MAX_REQUEST_AGE_SECS: i64 = 60 * 5;
+
+const SIGNATURE_VERSION: &str = "v0";
+
+type HmacSha256 = Hmac<Sha256>;
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum SignatureError {
+    MissingHeader(&'static str),
+    InvalidTimestamp,
+    Expired { age_secs: i64 },
+    Malformed,
+    Mismatch,
+}
+
+impl fmt::Display for SignatureError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SignatureError::MissingHeader(header) => write!(f, "missing {} header", header),
+            SignatureError::InvalidTimestamp => write!(f, "request timestamp is not a number"),
+            SignatureError::Expired { age_secs } => {
+                write!(f, "request timestamp is {}s away from now", age_secs)
+            }
+            SignatureError::Malformed => write!(f, "signature is not a v0 hex digest"),
+            SignatureError::Mismatch => write!(f, "signature does not match request body"),
+        }
+    }
+}
+
+impl std::error::Error for SignatureError {}
+
+// Checks the X-Slack-Signature and X-Slack-Request-Timestamp headers of a
+// request against its raw body. `now` is the current unix time in seconds and
+// is passed in so the replay window can be tested.
+pub fn verify(
+    signing_secret: &str,
+    headers: &HeaderMap,
+    body: &[u8],
+    now: i64,
+) -> Result<(), SignatureError> {
+    let timestamp: i64 = header_str(headers, TIMESTAMP_HEADER)?
+        .parse()
+        .map_err(|_| SignatureError::InvalidTimestamp)?;
+    let signature: &str = header_str(headers, SIGNATURE_HEADER)?;
+
+    let age_secs = (now - timestamp).abs();
+    if age_secs > MAX_REQUEST_AGE_SECS {
+        return Err(SignatureError::Expired { age_secs });
+    }
+
+    let digest: Vec<u8> = signature
+        .strip_prefix(SIGNATURE_VERSION)
+        .and_then(|rest| rest.strip_prefix('='))
+        .and_then(|hex_digest| hex::decode(hex_digest).ok())
+        .ok_or(SignatureError::Malformed)?;
+
+    // verify_slice compares in constant time so the digest can't be guessed
+    // byte by byte from response timings.
+    mac_for(signing_secret, timestamp, body)
+        .verify_slice(&digest)
+        .map_err(|_| SignatureError::Mismatch)
+}
+
+// Produces the value Slack would put in X-Slack-Signature for this body.
+// Used to sign fixture requests when replaying or testing them.
+pub fn sign(signing_secret: &str, timestamp: i64, body: &[u8]) -> String {
+    let digest = mac_for(signing_secret, timestamp, body)
+        .finalize()
+        .into_bytes();
+    format!("{}={}", SIGNATURE_VERSION, hex::encode(digest))
+}
+
+fn mac_for(signing_secret: &str, timestamp: i64, body: &[u8]) -> HmacSha256 {
+    let mut mac = HmacSha256::new_from_slice(signing_secret.as_bytes())
+        .expect("HMAC accepts keys of any length");
+    mac.update(format!("{}:{}:", SIGNATURE_VERSION, timestamp).as_bytes());
+    mac.update(body);
+    mac
+}
+
+fn header_str<'a>(headers: &'a HeaderMap, name: &'static str) -> Result<&'a str, SignatureError> {
+    headers
+        .get(name)
+        .and_then(|value| value.to_str().ok())
+        .ok_or(SignatureError::MissingHeader(name))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use lambda_http::http::HeaderValue;
+
+    // Example request from Slack's "Verifying requests from Slack" guide.
+    const SECRET: &str = "8f742231b10e8888abcd99yyyzzz85a5";
+    const TIMESTAMP: i64 = 1531420618;
+    const BODY: &str = "token=xyzz0WbapA4vBCDEFasx0q6G&team_id=T1DC2JH3J&team_domain=testteamnow&channel_id=G8PSS9T3V&channel_name=foobar&user_id=U2CERLKJA&user_name=roadrunner&command=%2Fwebhook-collect&text=&response_url=https%3A%2F%2Fhooks.slack.com%2Fcommands%2FT1DC2JH3J%2F397700885554%2F96rGlfmibIGlgcZRskXaIFfN&trigger_id=398738663015.47445629121.803a0bc887a14d10d2c447fce8b6703c";
+    const SIGNATURE: &str = "v0=a2114d57b48eac39b9ad189dd8316235a7b4a8d21a10bd27519666489c69b503";
+
+    fn signed_headers(timestamp: &str, signature: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(TIMESTAMP_HEADER, HeaderValue::from_str(timestamp).unwrap());
+        headers.insert(SIGNATURE_HEADER, HeaderValue::from_str(signature).unwrap());
+        headers
+    }
+
+    #[test]
+    fn accepts_known_good_request() {
+        let headers = signed_headers(&TIMESTAMP.to_string(), SIGNATURE);
+        assert_eq!(
+            verify(SECRET, &headers, BODY.as_bytes(), TIMESTAMP + 10),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn sign_matches_slack_example() {
+        assert_eq!(sign(SECRET, TIMESTAMP, BODY.as_bytes()), SIGNATURE);
+    }
+
+    #[test]
+    fn rejects_tampered_body() {
+        let headers = signed_headers(&TIMESTAMP.to_string(), SIGNATURE);
+        let tampered = BODY.replace("user_id=U2CERLKJA", "user_id=U00000000");
+        assert_eq!(
+            verify(SECRET, &headers, tampered.as_bytes(), TIMESTAMP),
+            Err(SignatureError::Mismatch)
+        );
+    }
+
+    #[test]
+    fn rejects_tampered_timestamp() {
+        let headers = signed_headers(&(TIMESTAMP + 1).to_string(), SIGNATURE);
+        assert_eq!(
+            verify(SECRET, &headers, BODY.as_bytes(), TIMESTAMP),
+            Err(SignatureError::Mismatch)
+        );
+    }
+
+    #[test]
+    fn rejects_wrong_secret() {
+        let headers = signed_headers(&TIMESTAMP.to_string(), SIGNATURE);
+        assert_eq!(
+            verify("not-the-secret", &headers, BODY.as_bytes(), TIMESTAMP),
+            Err(SignatureError::Mismatch)
+        );
+    }
+
+    #[test]
+    fn rejects_replayed_request() {
+        let headers = signed_headers(&TIMESTAMP.to_string(), SIGNATURE);
+        let now = TIMESTAMP + MAX_REQUEST_AGE_SECS + 1;
+        assert_eq!(
+            verify(SECRET, &headers, BODY.as_bytes(), now),
+            Err(SignatureError::Expired {
+                age_secs: MAX_REQUEST_AGE_SECS + 1
+            })
+        );
+    }
+
+    #[test]
+    fn rejects_missing_and_malformed_headers() {
+        assert_eq!(
+            verify(SECRET, &HeaderMap::new(), BODY.as_bytes(), TIMESTAMP),
+            Err(SignatureError::MissingHeader(TIMESTAMP_HEADER))
+        );
+        let headers = signed_headers("yesterday", SIGNATURE);
+        assert_eq!(
+            verify(SECRET, &headers, BODY.as_bytes(), TIMESTAMP),
+            Err(SignatureError::InvalidTimestamp)
+        );
+        let headers = signed_headers(&TIMESTAMP.to_string(), "v1=abcd");
+        assert_eq!(
+            verify(SECRET, &headers, BODY.as_bytes(), TIMESTAMP),
+            Err(SignatureError::Malformed)
+        );
+    }
+}
@@ -0,0 +1,56 @@
+use serde_derive::Deserialize;
+
+// Slack sends slash commands as application/x-www-form-urlencoded bodies
+// rather than JSON. Only the fields DevilBot uses are modelled.
+// Read more here: https://api.slack.com/interactivity/slash-commands
+#[derive(Debug, Clone, Deserialize)]
+pub struct SlashCommand {
+    // The command as typed, including the slash, e.g. "/devilbot".
+    pub command: String,
+    // Everything typed after the command. With "Escape channels, users, and
+    // links" enabled in the command's settings, mentions arrive as <@U123|name>.
+    #[serde(default)]
+    pub text: String,
+    // Where delayed replies are posted, for up to 30 minutes.
+    pub response_url: String,
+    // Lets us open a modal for the user, for 3 seconds.
+    pub trigger_id: String,
+    pub user_id: String,
+    pub channel_id: String,
+    pub team_id: String,
+}
+
+impl SlashCommand {
+    pub fn from_form(body: &[u8]) -> Result<Self, serde_urlencoded::de::Error> {
+        serde_urlencoded::from_bytes(body)
+    }
+
+    // The command's name without the slash, lowercased.
+    pub fn name(&self) -> String {
+        self.command.trim_start_matches('/').to_lowercase()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_form_bodies() {
+        let body = "token=gIkuvaNzQIHg97ATvDxqgjtO&team_id=T0001&team_domain=example\
+            &channel_id=C2147483705&channel_name=test&user_id=U2147483697&user_name=Steve\
+            &command=%2Fdevilbot&text=buns+top+%3C%40U0DEVILFAN%7Csun%3E\
+            &response_url=https%3A%2F%2Fhooks.slack.com%2Fcommands%2F1234%2F5678\
+            &trigger_id=13345224609.738474920.8088930838d88f008e0";
+
+        let command = SlashCommand::from_form(body.as_bytes()).unwrap();
+
+        assert_eq!(command.name(), "devilbot");
+        assert_eq!(command.text, "buns top <@U0DEVILFAN|sun>");
+        assert_eq!(
+            command.response_url,
+            "https://hooks.slack.com/commands/1234/5678"
+        );
+        assert_eq!(command.channel_id, "C2147483705");
+    }
+}
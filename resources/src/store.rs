@@ -0,0 +1,139 @@
+// Everything DevilBot remembers goes through the Store trait, so commands
+// never talk to DynamoDB directly. In production it is backed by DynamoDB
+// (see aws/dynamo.rs); tests and local runs use the in-memory store instead.
+
+pub mod memory;
+
+use async_trait::async_trait;
+use lambda_http::Error;
+use std::collections::HashMap;
+
+// An attribute value. DynamoDB has many more types, but so far every
+// table only holds strings and whole numbers.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Value {
+    S(String),
+    N(i64),
+}
+
+impl Value {
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Value::S(value) => Some(value),
+            Value::N(_) => None,
+        }
+    }
+
+    pub fn as_i64(&self) -> Option<i64> {
+        match self {
+            Value::N(value) => Some(*value),
+            Value::S(_) => None,
+        }
+    }
+}
+
+impl From<&str> for Value {
+    fn from(value: &str) -> Self {
+        Value::S(value.to_string())
+    }
+}
+
+impl From<String> for Value {
+    fn from(value: String) -> Self {
+        Value::S(value)
+    }
+}
+
+impl From<i64> for Value {
+    fn from(value: i64) -> Self {
+        Value::N(value)
+    }
+}
+
+// A whole item, keyed by attribute name. Items read back from a store
+// include their key attributes.
+pub type Item = HashMap<String, Value>;
+
+// The primary key of an item: its partition key, plus its sort key for
+// tables that have one.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Key {
+    pub partition: (String, Value),
+    pub sort: Option<(String, Value)>,
+}
+
+impl Key {
+    pub fn new(name: &str, value: impl Into<Value>) -> Self {
+        Key {
+            partition: (name.to_string(), value.into()),
+            sort: None,
+        }
+    }
+
+    pub fn with_sort(mut self, name: &str, value: impl Into<Value>) -> Self {
+        self.sort = Some((name.to_string(), value.into()));
+        self
+    }
+
+    // The key attributes on their own, as they appear in a stored item.
+    pub fn to_item(&self) -> Item {
+        let mut item = Item::new();
+        item.insert(self.partition.0.clone(), self.partition.1.clone());
+        if let Some((name, value)) = &self.sort {
+            item.insert(name.clone(), value.clone());
+        }
+        item
+    }
+
+    // Whether `item` is the item this key points at.
+    pub fn matches(&self, item: &Item) -> bool {
+        let (name, value) = &self.partition;
+        item.get(name) == Some(value)
+            && match &self.sort {
+                Some((name, value)) => item.get(name) == Some(value),
+                None => true,
+            }
+    }
+}
+
+// Which items a query should return.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Query {
+    // Every item in the table, in no particular order. Only meant for
+    // small tables like the buns counts.
+    Scan,
+    // The items sharing one partition key, in sort key order. With
+    // `sort_from` set, only the items whose sort key is at least that value.
+    Partition {
+        name: String,
+        value: Value,
+        sort_from: Option<(String, Value)>,
+    },
+}
+
+#[async_trait]
+pub trait Store: Send + Sync {
+    async fn get(&self, table: &str, key: &Key) -> Result<Option<Item>, Error>;
+
+    // Writes the item under `key`, replacing whatever was there.
+    async fn put(&self, table: &str, key: &Key, attributes: Item) -> Result<(), Error>;
+
+    // Like put, but leaves an existing item alone. Returns false if there
+    // already was an item with that key.
+    async fn put_if_absent(&self, table: &str, key: &Key, attributes: Item) -> Result<bool, Error>;
+
+    // Adds `delta` (which may be negative) to a numeric attribute and
+    // returns its new value. A missing item or attribute counts as zero.
+    // Concurrent increments must never overwrite each other.
+    async fn increment(
+        &self,
+        table: &str,
+        key: &Key,
+        attribute: &str,
+        delta: i64,
+    ) -> Result<i64, Error>;
+
+    async fn query(&self, table: &str, query: &Query) -> Result<Vec<Item>, Error>;
+
+    async fn delete(&self, table: &str, key: &Key) -> Result<(), Error>;
+}
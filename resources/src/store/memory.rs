@@ -0,0 +1,207 @@
+use crate::store::{Item, Key, Query, Store, Value};
+use async_trait::async_trait;
+use lambda_http::Error;
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+// Keeps every table in memory. Only suitable for tests and local runs,
+// since every Lambda instance would get its own copy. Tables spring into
+// existence on first use.
+#[derive(Default)]
+pub struct MemoryStore {
+    tables: Mutex<HashMap<String, Vec<Item>>>,
+    // Each table's sort key name, learnt from the keys it is written with,
+    // so partition queries can come back in sort key order like DynamoDB's.
+    sort_keys: Mutex<HashMap<String, String>>,
+}
+
+impl MemoryStore {
+    fn with_table<T>(&self, table: &str, f: impl FnOnce(&mut Vec<Item>) -> T) -> T {
+        let mut tables = self.tables.lock().unwrap();
+        f(tables.entry(table.to_string()).or_default())
+    }
+
+    // Like with_table, for writes through `key`.
+    fn write<T>(&self, table: &str, key: &Key, f: impl FnOnce(&mut Vec<Item>) -> T) -> T {
+        if let Some((sort_key, _)) = &key.sort {
+            let mut sort_keys = self.sort_keys.lock().unwrap();
+            sort_keys.insert(table.to_string(), sort_key.clone());
+        }
+        self.with_table(table, f)
+    }
+}
+
+fn position(items: &[Item], key: &Key) -> Option<usize> {
+    items.iter().position(|item| key.matches(item))
+}
+
+fn with_key(key: &Key, attributes: Item) -> Item {
+    let mut item = attributes;
+    item.extend(key.to_item());
+    item
+}
+
+#[async_trait]
+impl Store for MemoryStore {
+    async fn get(&self, table: &str, key: &Key) -> Result<Option<Item>, Error> {
+        Ok(self.with_table(table, |items| {
+            position(items, key).map(|index| items[index].clone())
+        }))
+    }
+
+    async fn put(&self, table: &str, key: &Key, attributes: Item) -> Result<(), Error> {
+        self.write(table, key, |items| {
+            let item = with_key(key, attributes);
+            match position(items, key) {
+                Some(index) => items[index] = item,
+                None => items.push(item),
+            }
+        });
+        Ok(())
+    }
+
+    async fn put_if_absent(&self, table: &str, key: &Key, attributes: Item) -> Result<bool, Error> {
+        Ok(self.write(table, key, |items| match position(items, key) {
+            Some(_) => false,
+            None => {
+                items.push(with_key(key, attributes));
+                true
+            }
+        }))
+    }
+
+    async fn increment(
+        &self,
+        table: &str,
+        key: &Key,
+        attribute: &str,
+        delta: i64,
+    ) -> Result<i64, Error> {
+        self.write(table, key, |items| {
+            let index = match position(items, key) {
+                Some(index) => index,
+                None => {
+                    items.push(key.to_item());
+                    items.len() - 1
+                }
+            };
+            let value = items[index]
+                .entry(attribute.to_string())
+                .or_insert(Value::N(0));
+            match value {
+                Value::N(number) => {
+                    *number += delta;
+                    Ok(*number)
+                }
+                Value::S(_) => Err(format!("{} is not a number", attribute).into()),
+            }
+        })
+    }
+
+    async fn query(&self, table: &str, query: &Query) -> Result<Vec<Item>, Error> {
+        let items: Vec<Item> = self.with_table(table, |items| items.clone());
+        match query {
+            Query::Scan => Ok(items),
+            Query::Partition {
+                name,
+                value,
+                sort_from,
+            } => {
+                let mut items: Vec<Item> = items
+                    .into_iter()
+                    .filter(|item| item.get(name) == Some(value))
+                    .collect();
+                if let Some((sort_key, from)) = sort_from {
+                    items.retain(|item| item.get(sort_key).is_some_and(|sort| sort >= from));
+                }
+                let sort_key: Option<String> = match sort_from {
+                    Some((sort_key, _)) => Some(sort_key.clone()),
+                    None => self.sort_keys.lock().unwrap().get(table).cloned(),
+                };
+                if let Some(sort_key) = sort_key {
+                    items.sort_by(|a, b| a.get(&sort_key).cmp(&b.get(&sort_key)));
+                }
+                Ok(items)
+            }
+        }
+    }
+
+    async fn delete(&self, table: &str, key: &Key) -> Result<(), Error> {
+        self.with_table(table, |items| items.retain(|item| !key.matches(item)));
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn increments_from_zero() {
+        let store = MemoryStore::default();
+        let key = Key::new("user_id", "U123");
+
+        assert_eq!(store.increment("buns", &key, "buns", 1).await.unwrap(), 1);
+        assert_eq!(store.increment("buns", &key, "buns", -3).await.unwrap(), -2);
+
+        let item = store.get("buns", &key).await.unwrap().unwrap();
+        assert_eq!(item.get("buns"), Some(&Value::N(-2)));
+        assert_eq!(store.query("buns", &Query::Scan).await.unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn queries_a_partition_from_a_sort_key() {
+        let store = MemoryStore::default();
+        for (giver, given_at) in [("U1", 30), ("U1", 10), ("U2", 20), ("U1", 20)] {
+            let key = Key::new("giver", giver).with_sort("given_at", given_at as i64);
+            store.put("karma", &key, Item::new()).await.unwrap();
+        }
+
+        let query = Query::Partition {
+            name: "giver".to_string(),
+            value: Value::from("U1"),
+            sort_from: Some(("given_at".to_string(), Value::N(20))),
+        };
+        let given_at: Vec<Option<Value>> = store
+            .query("karma", &query)
+            .await
+            .unwrap()
+            .iter()
+            .map(|item| item.get("given_at").cloned())
+            .collect();
+        assert_eq!(given_at, vec![Some(Value::N(20)), Some(Value::N(30))]);
+
+        store
+            .delete("karma", &Key::new("giver", "U2").with_sort("given_at", 20))
+            .await
+            .unwrap();
+        let remaining = store.query("karma", &Query::Scan).await.unwrap();
+        assert_eq!(remaining.len(), 3);
+    }
+
+    #[tokio::test]
+    async fn queries_a_whole_partition_in_sort_key_order() {
+        let store = MemoryStore::default();
+        for given_at in [30, 10, 20] {
+            let key = Key::new("giver", "U1").with_sort("given_at", given_at as i64);
+            store.put("karma", &key, Item::new()).await.unwrap();
+        }
+
+        let query = Query::Partition {
+            name: "giver".to_string(),
+            value: Value::from("U1"),
+            sort_from: None,
+        };
+        let given_at: Vec<Option<Value>> = store
+            .query("karma", &query)
+            .await
+            .unwrap()
+            .iter()
+            .map(|item| item.get("given_at").cloned())
+            .collect();
+        assert_eq!(
+            given_at,
+            vec![Some(Value::N(10)), Some(Value::N(20)), Some(Value::N(30))]
+        );
+    }
+}
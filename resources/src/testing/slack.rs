@@ -0,0 +1,120 @@
+use hyper::service::{make_service_fn, service_fn};
+use hyper::{Body, Request, Response, Server};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::convert::Infallible;
+use std::fmt;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+
+// One Web API call the fake received.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SlackCall {
+    // e.g. "chat.postMessage"
+    pub method: String,
+    pub body: Value,
+}
+
+impl fmt::Display for SlackCall {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {}", self.method, self.body)
+    }
+}
+
+// A Slack Web API on localhost that accepts every call, records it, and
+// answers just enough for SlackClient to decode the response. Tests can
+// script a different answer per method, e.g. an error.
+pub struct FakeSlack {
+    base_url: String,
+    shared: Arc<Shared>,
+}
+
+#[derive(Default)]
+struct Shared {
+    calls: Mutex<Vec<SlackCall>>,
+    // HTTP status and body, by method.
+    scripted: Mutex<HashMap<String, (u16, Value)>>,
+}
+
+impl FakeSlack {
+    pub async fn start() -> Self {
+        let shared: Arc<Shared> = Arc::default();
+        let server_shared = shared.clone();
+        let make_service = make_service_fn(move |_| {
+            let shared = server_shared.clone();
+            async move {
+                Ok::<_, Infallible>(service_fn(move |request: Request<Body>| {
+                    let shared = shared.clone();
+                    async move { Ok::<_, Infallible>(record(request, &shared).await) }
+                }))
+            }
+        });
+        let server = Server::bind(&SocketAddr::from(([127, 0, 0, 1], 0))).serve(make_service);
+        let base_url = format!("http://{}", server.local_addr());
+        tokio::spawn(server);
+        FakeSlack { base_url, shared }
+    }
+
+    // Answers every later call to `method` with `response`, e.g.
+    // json!({"ok": false, "error": "channel_not_found"}).
+    pub fn respond_with(&self, method: &str, response: Value) {
+        self.respond_with_status(method, 200, response);
+    }
+
+    // Like respond_with, with an HTTP status other than 200 OK.
+    pub fn respond_with_status(&self, method: &str, status: u16, response: Value) {
+        let mut scripted = self.shared.scripted.lock().unwrap();
+        scripted.insert(method.to_string(), (status, response));
+    }
+
+    pub fn base_url(&self) -> &str {
+        &self.base_url
+    }
+
+    // A response_url for slash commands. Responses posted to it are recorded
+    // as calls to the "response_url" method.
+    pub fn response_url(&self) -> String {
+        format!("{}/response_url", self.base_url)
+    }
+
+    // Every call received so far, oldest first.
+    pub fn calls(&self) -> Vec<SlackCall> {
+        self.shared.calls.lock().unwrap().clone()
+    }
+
+    // Like calls, but forgets them.
+    pub fn take_calls(&self) -> Vec<SlackCall> {
+        std::mem::take(&mut *self.shared.calls.lock().unwrap())
+    }
+}
+
+async fn record(request: Request<Body>, shared: &Shared) -> Response<Body> {
+    let method: String = request.uri().path().trim_start_matches('/').to_string();
+    let body: Value = match hyper::body::to_bytes(request.into_body()).await {
+        Ok(bytes) => serde_json::from_slice(&bytes).unwrap_or(Value::Null),
+        Err(_) => Value::Null,
+    };
+    let scripted: Option<(u16, Value)> = shared.scripted.lock().unwrap().get(&method).cloned();
+    let (status, response) = scripted.unwrap_or_else(|| (200, response_for(&method, &body)));
+    shared
+        .calls
+        .lock()
+        .unwrap()
+        .push(SlackCall { method, body });
+    Response::builder()
+        .status(status)
+        .body(Body::from(response.to_string()))
+        .expect("scripted statuses are valid")
+}
+
+fn response_for(method: &str, request: &Value) -> Value {
+    match method {
+        "chat.postMessage" => json!({
+            "ok": true,
+            "channel": request["channel"],
+            "ts": "1700000000.000100",
+        }),
+        "conversations.open" => json!({"ok": true, "channel": {"id": "D0FAKEDM"}}),
+        _ => json!({"ok": true}),
+    }
+}
@@ -0,0 +1,185 @@
+use crate::store::memory::MemoryStore;
+use crate::store::{Item, Key, Query, Store, Value};
+use async_trait::async_trait;
+use lambda_http::Error;
+use std::fmt;
+use std::sync::Mutex;
+
+// One write made through a RecordingStore.
+#[derive(Debug, Clone, PartialEq)]
+pub enum StoreWrite {
+    Put {
+        table: String,
+        key: Key,
+        attributes: Item,
+    },
+    PutIfAbsent {
+        table: String,
+        key: Key,
+        attributes: Item,
+        written: bool,
+    },
+    Increment {
+        table: String,
+        key: Key,
+        attribute: String,
+        delta: i64,
+    },
+    Delete {
+        table: String,
+        key: Key,
+    },
+}
+
+fn format_key(key: &Key) -> String {
+    let mut parts = vec![format_pair(&key.partition.0, &key.partition.1)];
+    if let Some((name, value)) = &key.sort {
+        parts.push(format_pair(name, value));
+    }
+    parts.join(", ")
+}
+
+fn format_item(item: &Item) -> String {
+    let mut pairs: Vec<String> = item
+        .iter()
+        .map(|(name, value)| format_pair(name, value))
+        .collect();
+    pairs.sort();
+    format!("{{{}}}", pairs.join(", "))
+}
+
+fn format_pair(name: &str, value: &Value) -> String {
+    match value {
+        Value::S(value) => format!("{}={:?}", name, value),
+        Value::N(value) => format!("{}={}", name, value),
+    }
+}
+
+impl fmt::Display for StoreWrite {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StoreWrite::Put {
+                table,
+                key,
+                attributes,
+            } => write!(
+                f,
+                "put {} [{}] {}",
+                table,
+                format_key(key),
+                format_item(attributes)
+            ),
+            StoreWrite::PutIfAbsent {
+                table,
+                key,
+                attributes,
+                written,
+            } => write!(
+                f,
+                "put_if_absent {} [{}] {}{}",
+                table,
+                format_key(key),
+                format_item(attributes),
+                if *written { "" } else { " (already there)" }
+            ),
+            StoreWrite::Increment {
+                table,
+                key,
+                attribute,
+                delta,
+            } => write!(
+                f,
+                "increment {} [{}] {} by {}",
+                table,
+                format_key(key),
+                attribute,
+                delta
+            ),
+            StoreWrite::Delete { table, key } => {
+                write!(f, "delete {} [{}]", table, format_key(key))
+            }
+        }
+    }
+}
+
+// An in-memory store that also keeps a log of every write, so callers can
+// see what the handler would have changed in DynamoDB.
+#[derive(Default)]
+pub struct RecordingStore {
+    inner: MemoryStore,
+    writes: Mutex<Vec<StoreWrite>>,
+}
+
+impl RecordingStore {
+    // Every write so far, oldest first.
+    pub fn writes(&self) -> Vec<StoreWrite> {
+        self.writes.lock().unwrap().clone()
+    }
+
+    // Like writes, but forgets them.
+    pub fn take_writes(&self) -> Vec<StoreWrite> {
+        std::mem::take(&mut *self.writes.lock().unwrap())
+    }
+
+    fn record(&self, write: StoreWrite) {
+        self.writes.lock().unwrap().push(write);
+    }
+}
+
+#[async_trait]
+impl Store for RecordingStore {
+    async fn get(&self, table: &str, key: &Key) -> Result<Option<Item>, Error> {
+        self.inner.get(table, key).await
+    }
+
+    async fn put(&self, table: &str, key: &Key, attributes: Item) -> Result<(), Error> {
+        self.record(StoreWrite::Put {
+            table: table.to_string(),
+            key: key.clone(),
+            attributes: attributes.clone(),
+        });
+        self.inner.put(table, key, attributes).await
+    }
+
+    async fn put_if_absent(&self, table: &str, key: &Key, attributes: Item) -> Result<bool, Error> {
+        let written = self
+            .inner
+            .put_if_absent(table, key, attributes.clone())
+            .await?;
+        self.record(StoreWrite::PutIfAbsent {
+            table: table.to_string(),
+            key: key.clone(),
+            attributes,
+            written,
+        });
+        Ok(written)
+    }
+
+    async fn increment(
+        &self,
+        table: &str,
+        key: &Key,
+        attribute: &str,
+        delta: i64,
+    ) -> Result<i64, Error> {
+        self.record(StoreWrite::Increment {
+            table: table.to_string(),
+            key: key.clone(),
+            attribute: attribute.to_string(),
+            delta,
+        });
+        self.inner.increment(table, key, attribute, delta).await
+    }
+
+    async fn query(&self, table: &str, query: &Query) -> Result<Vec<Item>, Error> {
+        self.inner.query(table, query).await
+    }
+
+    async fn delete(&self, table: &str, key: &Key) -> Result<(), Error> {
+        self.record(StoreWrite::Delete {
+            table: table.to_string(),
+            key: key.clone(),
+        });
+        self.inner.delete(table, key).await
+    }
+}
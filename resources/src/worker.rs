@@ -0,0 +1,159 @@
+use crate::queue::Job;
+use crate::slack::client::SlackError;
+use crate::slack::events::Envelope;
+use crate::slack::interactions::Interaction;
+use crate::slack::rate_limit::RetryPolicy;
+use crate::slack::slash_commands::SlashCommand;
+use crate::AppState;
+use lambda_http::Error;
+use std::sync::Arc;
+use tokio::sync::mpsc::UnboundedReceiver;
+
+// Runs one job. An error means the job should be tried again later.
+// Failures a retry can't fix, like Slack answering user_not_found, are
+// logged and the job counts as done. A retry skips the commands that already
+// finished (see CommandRegistry::dispatch), but commands should still cope
+// with running twice.
+pub async fn process(job: &Job, state: &AppState) -> Result<(), Error> {
+    match run(job, state).await {
+        Err(err) if is_permanent(&err) => {
+            log::error!("Job failed for good, not retrying: {}", err);
+            Ok(())
+        }
+        result => result,
+    }
+}
+
+// Whether an error would only happen again if the job was retried.
+pub fn is_permanent(err: &Error) -> bool {
+    err.downcast_ref::<SlackError>()
+        .is_some_and(SlackError::is_permanent)
+}
+
+async fn run(job: &Job, state: &AppState) -> Result<(), Error> {
+    match job {
+        Job::EventCallback { body } => match serde_json::from_str(body)? {
+            Envelope::EventCallback(callback) => crate::intercept_command(&callback, state).await,
+            envelope => Err(format!("not an event callback: {:?}", envelope).into()),
+        },
+        Job::SlashCommand { body } => {
+            let command = SlashCommand::from_form(body.as_bytes())?;
+            crate::run_slash_command(&command, state).await
+        }
+        Job::Interaction { body } => {
+            let interaction = Interaction::from_form(body.as_bytes())?;
+            crate::run_interaction(&interaction, state).await
+        }
+    }
+}
+
+// Runs a job, retrying failures with backoff. Gives up after the policy's
+// retries and returns the last error, at which point the job is dead.
+pub async fn process_with_retries(
+    job: &Job,
+    state: &AppState,
+    retry_policy: &RetryPolicy,
+) -> Result<(), Error> {
+    let mut attempt: u32 = 0;
+    loop {
+        match process(job, state).await {
+            Ok(()) => return Ok(()),
+            Err(err) if attempt >= retry_policy.max_retries => return Err(err),
+            Err(err) => {
+                let backoff = retry_policy.backoff(attempt);
+                log::info!("Job failed: {}, retrying in {:?}", err, backoff);
+                tokio::time::sleep(backoff).await;
+            }
+        }
+        attempt += 1;
+    }
+}
+
+// The in-process worker behind queue::local. SQS keeps failed messages in a
+// dead-letter queue; here a dead job is logged in full so it isn't lost.
+pub async fn run_local(mut jobs: UnboundedReceiver<Job>, state: Arc<AppState>) {
+    let retry_policy = RetryPolicy::default();
+    while let Some(job) = jobs.recv().await {
+        if let Err(err) = process_with_retries(&job, &state, &retry_policy).await {
+            log::error!(
+                "Dead job after {} retries: {}",
+                retry_policy.max_retries,
+                err
+            );
+            log::error!("{}", serde_json::to_string(&job).unwrap_or_default());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::store::{Key, Store, Value as StoreValue};
+    use crate::testing::Harness;
+    use serde_json::{json, Value};
+    use std::time::Duration;
+
+    fn retry_policy() -> RetryPolicy {
+        RetryPolicy {
+            max_retries: 2,
+            base_delay: Duration::from_millis(1),
+            max_delay: Duration::from_millis(1),
+        }
+    }
+
+    #[tokio::test]
+    async fn does_not_retry_jobs_that_fail_for_good() {
+        let harness = Harness::start().await.unwrap();
+        // The fake Slack refuses to open DMs, so onboarding always fails.
+        harness.slack.respond_with(
+            "conversations.open",
+            json!({"ok": false, "error": "user_not_found"}),
+        );
+        let job = Job::EventCallback {
+            body: include_str!("../fixtures/events/06_team_join.json").to_string(),
+        };
+
+        let result = process_with_retries(&job, &harness.state, &retry_policy()).await;
+
+        assert!(result.is_ok());
+        assert_eq!(harness.slack.calls().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn retries_only_the_commands_that_failed() {
+        let harness = Harness::start().await.unwrap();
+        // Slack keeps failing to post karma's reply, after the heart reaction
+        // and the karma change went through.
+        harness
+            .slack
+            .respond_with_status("chat.postMessage", 500, json!({"ok": false}));
+        let mut event: Value =
+            serde_json::from_str(include_str!("../fixtures/events/02_ping.json")).unwrap();
+        event["event"]["text"] = json!("!heart bagels++");
+        let job = Job::EventCallback {
+            body: event.to_string(),
+        };
+
+        let result = process_with_retries(&job, &harness.state, &retry_policy()).await;
+
+        assert!(result.is_err());
+        let methods: Vec<String> = harness
+            .slack
+            .calls()
+            .into_iter()
+            .map(|call| call.method)
+            .collect();
+        assert_eq!(
+            methods,
+            vec![
+                "reactions.add",
+                "chat.postMessage",
+                "chat.postMessage",
+                "chat.postMessage"
+            ]
+        );
+        let key = Key::new("target", "bagels");
+        let item = harness.store.get("karma", &key).await.unwrap().unwrap();
+        assert_eq!(item.get("karma"), Some(&StoreValue::N(1)));
+    }
+}
@@ -0,0 +1,437 @@
+// End-to-end tests: Slack events go in through the handler, and we check the
+// exact Web API calls DevilBot makes against a fake Slack and what it writes
+// to an in-memory store.
+
+use devil_bot_rust::store::{Key, Store, Value};
+use devil_bot_rust::testing::slack::SlackCall;
+use devil_bot_rust::testing::Harness;
+use lambda_http::http::StatusCode;
+use serde_json::{json, Value as Json};
+
+const URL_VERIFICATION: &str = include_str!("../fixtures/events/01_url_verification.json");
+const PING: &str = include_str!("../fixtures/events/02_ping.json");
+const BUNS: &str = include_str!("../fixtures/events/03_buns.json");
+const TEAM_JOIN: &str = include_str!("../fixtures/events/06_team_join.json");
+const APP_HOME_OPENED: &str = include_str!("../fixtures/events/07_app_home_opened.json");
+
+// A message event from U0DEVILFAN in #devil-bot-test.
+fn message(event_id: &str, text: &str, ts: &str) -> Vec<u8> {
+    let mut event: Json = serde_json::from_str(PING).unwrap();
+    event["event_id"] = json!(event_id);
+    event["event"]["text"] = json!(text);
+    event["event"]["ts"] = json!(ts);
+    event.to_string().into_bytes()
+}
+
+// A slash command from U0DEVILFAN in #devil-bot-test, answered through the
+// fake Slack.
+fn slash_command(harness: &Harness, command: &str, text: &str) -> String {
+    serde_urlencoded::to_string([
+        ("command", command),
+        ("text", text),
+        ("response_url", &harness.slack.response_url()),
+        ("trigger_id", "13345224609.738474920.8088930838d88f008e0"),
+        ("user_id", "U0DEVILFAN"),
+        ("channel_id", "C0351GJ62Q0"),
+        ("team_id", "T0DEVILS"),
+    ])
+    .unwrap()
+}
+
+fn call(method: &str, body: Json) -> SlackCall {
+    SlackCall {
+        method: method.to_string(),
+        body,
+    }
+}
+
+#[tokio::test]
+async fn ping_replies_pong() {
+    let harness = Harness::start().await.unwrap();
+
+    let response = harness.send(PING).await.unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(
+        harness.slack.calls(),
+        vec![call(
+            "chat.postMessage",
+            json!({"channel": "C0351GJ62Q0", "text": "pong"})
+        )]
+    );
+    assert!(harness.store.writes().is_empty());
+}
+
+#[tokio::test]
+async fn buns_counts_and_reacts() {
+    let harness = Harness::start().await.unwrap();
+
+    harness.send(BUNS).await.unwrap();
+    harness
+        .send(message("Ev0356A5S990", "!buns", "1645903871.000100"))
+        .await
+        .unwrap();
+
+    let key = Key::new("user_id", "U0DEVILFAN");
+    let item = harness.store.get("buns", &key).await.unwrap().unwrap();
+    assert_eq!(item.get("buns"), Some(&Value::N(2)));
+    assert_eq!(
+        harness.slack.calls(),
+        vec![
+            call(
+                "reactions.add",
+                json!({"channel": "C0351GJ62Q0", "name": "buns", "timestamp": "1645903870.000100"})
+            ),
+            call(
+                "reactions.add",
+                json!({"channel": "C0351GJ62Q0", "name": "buns", "timestamp": "1645903871.000100"})
+            ),
+        ]
+    );
+}
+
+#[tokio::test]
+async fn buns_shrugs_off_existing_reactions() {
+    let harness = Harness::start().await.unwrap();
+    harness.slack.respond_with(
+        "reactions.add",
+        json!({"ok": false, "error": "already_reacted"}),
+    );
+
+    let response = harness.send(BUNS).await.unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+    let key = Key::new("user_id", "U0DEVILFAN");
+    let item = harness.store.get("buns", &key).await.unwrap().unwrap();
+    assert_eq!(item.get("buns"), Some(&Value::N(1)));
+}
+
+#[tokio::test]
+async fn buns_top_posts_a_leaderboard() {
+    let harness = Harness::start().await.unwrap();
+    harness.send(BUNS).await.unwrap();
+    harness.slack.take_calls();
+
+    harness
+        .send(message("Ev0356A5S991", "!buns top 3", "1645903880.000200"))
+        .await
+        .unwrap();
+
+    assert_eq!(
+        harness.slack.calls(),
+        vec![call(
+            "chat.postMessage",
+            json!({
+                "channel": "C0351GJ62Q0",
+                "text": "Buns leaderboard",
+                "thread_ts": "1645903880.000200",
+                "blocks": [
+                    {
+                        "type": "header",
+                        "text": {"type": "plain_text", "text": "Buns leaderboard", "emoji": true}
+                    },
+                    {
+                        "type": "section",
+                        "text": {"type": "mrkdwn", "text": "1. <@U0DEVILFAN> 1 :buns:"}
+                    },
+                    {
+                        "type": "actions",
+                        "elements": [{
+                            "type": "button",
+                            "text": {"type": "plain_text", "text": "Refresh", "emoji": true},
+                            "action_id": "buns_refresh_top",
+                            "value": "3"
+                        }]
+                    }
+                ]
+            })
+        )]
+    );
+}
+
+#[tokio::test]
+async fn refreshing_the_buns_leaderboard_replaces_it() {
+    let harness = Harness::start().await.unwrap();
+    harness.send(BUNS).await.unwrap();
+    harness.slack.take_calls();
+    let payload = json!({
+        "type": "block_actions",
+        "user": {"id": "U0DEVILFAN"},
+        "trigger_id": "12466734323.1395872398",
+        "response_url": harness.slack.response_url(),
+        "channel": {"id": "C0351GJ62Q0"},
+        "message": {"ts": "1700000000.000100", "text": "Buns leaderboard"},
+        "actions": [{"action_id": "buns_refresh_top", "block_id": "b", "value": "3"}]
+    });
+    let body = serde_urlencoded::to_string([("payload", payload.to_string())]).unwrap();
+
+    let response = harness.send(body).await.unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+    let calls = harness.slack.calls();
+    assert_eq!(calls.len(), 1);
+    assert_eq!(calls[0].method, "response_url");
+    assert_eq!(calls[0].body["replace_original"], true);
+    assert_eq!(
+        calls[0].body["blocks"][1]["text"]["text"],
+        "1. <@U0DEVILFAN> 1 :buns:"
+    );
+}
+
+#[tokio::test]
+async fn onboard_user_sends_a_welcome_dm() {
+    let harness = Harness::start().await.unwrap();
+
+    harness.send(TEAM_JOIN).await.unwrap();
+
+    let calls = harness.slack.calls();
+    assert_eq!(calls.len(), 2);
+    assert_eq!(
+        calls[0],
+        call("conversations.open", json!({"users": "U0NEWBIE"}))
+    );
+    assert_eq!(calls[1].method, "chat.postMessage");
+    assert_eq!(calls[1].body["channel"], "D0FAKEDM");
+    assert_eq!(
+        calls[1].body["text"],
+        ":codedevils_flash: Hey welcome to CodeDevils Sun :codedevils_flash: I am DevilBot, say `!help` to see everything I can do. Here's what you should know first: \
+        This Slack workspace serves as the main communication platform for all things CodeDevils :partywizard: All our announcements can be found in the <#C30L07P18> channel. \
+        This includes all meetings and meeting recordings! I'd like you to go to the <#CMGU8033K> channel and introduce yourself. After that, come on over to\
+        <#C2N5P84BD>. Most of my creators are there all day."
+    );
+}
+
+#[tokio::test]
+async fn app_home_shows_stats_and_upcoming_events() {
+    let harness = Harness::with_vars(|name| match name {
+        "CLUB_EVENTS_TABLE_NAME" => Some("club-events".to_string()),
+        _ => None,
+    })
+    .await
+    .unwrap();
+    harness.send(BUNS).await.unwrap();
+    let hack_night = Key::new("event_id", "hack-night");
+    let attributes = [
+        ("title".to_string(), Value::from("Hack Night")),
+        ("starts_at".to_string(), Value::from(4102444800)),
+        ("location".to_string(), Value::from("BYENG 210")),
+    ];
+    harness
+        .store
+        .put("club-events", &hack_night, attributes.into())
+        .await
+        .unwrap();
+    harness.slack.take_calls();
+
+    harness.send(APP_HOME_OPENED).await.unwrap();
+
+    let calls = harness.slack.calls();
+    assert_eq!(calls.len(), 1);
+    assert_eq!(calls[0].method, "views.publish");
+    assert_eq!(calls[0].body["user_id"], "U0DEVILFAN");
+    let view = &calls[0].body["view"];
+    assert_eq!(view["type"], "home");
+    assert_eq!(
+        view["blocks"][1]["fields"],
+        json!([
+            {"type": "mrkdwn", "text": "*Buns*\n1 :buns:"},
+            {"type": "mrkdwn", "text": "*Karma*\n0"}
+        ])
+    );
+    assert_eq!(
+        view["blocks"][4]["text"]["text"],
+        "*Hack Night*\n<!date^4102444800^{date_short_pretty} at {time}|soon> · BYENG 210"
+    );
+}
+
+#[tokio::test]
+async fn help_explains_a_command_by_its_alias() {
+    let harness = Harness::start().await.unwrap();
+
+    harness
+        .send(message(
+            "Ev0356A5S993",
+            "!help commands",
+            "1645903895.000350",
+        ))
+        .await
+        .unwrap();
+
+    assert_eq!(
+        harness.slack.calls(),
+        vec![call(
+            "chat.postMessage",
+            json!({
+                "channel": "C0351GJ62Q0",
+                "text": "*`!help [command]`*\n\
+                    Lists everything DevilBot can do, or explains one command.\n\
+                    Also: `!commands`\n\
+                    _Works in every channel I'm in._",
+                "thread_ts": "1645903895.000350"
+            })
+        )]
+    );
+}
+
+#[tokio::test]
+async fn unknown_commands_get_a_reply() {
+    let harness = Harness::start().await.unwrap();
+
+    harness
+        .send(message("Ev0356A5S992", "!dance", "1645903890.000300"))
+        .await
+        .unwrap();
+
+    assert_eq!(
+        harness.slack.calls(),
+        vec![call(
+            "chat.postMessage",
+            json!({
+                "channel": "C0351GJ62Q0",
+                "text": "I don't know the command `dance`.",
+                "thread_ts": "1645903890.000300"
+            })
+        )]
+    );
+}
+
+#[tokio::test]
+async fn chatter_after_the_prefix_is_ignored() {
+    let harness = Harness::start().await.unwrap();
+
+    harness
+        .send(message("Ev0356A5S995", "! wow", "1645903895.000500"))
+        .await
+        .unwrap();
+    harness
+        .send(message("Ev0356A5S996", "!!!", "1645903896.000600"))
+        .await
+        .unwrap();
+
+    assert!(harness.slack.calls().is_empty());
+}
+
+#[tokio::test]
+async fn slash_commands_reply_through_the_response_url() {
+    let harness = Harness::start().await.unwrap();
+
+    let response = harness
+        .send(slash_command(&harness, "/devilbot", "ping"))
+        .await
+        .unwrap();
+    harness
+        .send(slash_command(&harness, "/buns", "me"))
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(
+        harness.slack.calls(),
+        vec![
+            call(
+                "response_url",
+                json!({"response_type": "in_channel", "text": "pong"})
+            ),
+            call(
+                "response_url",
+                json!({
+                    "response_type": "in_channel",
+                    "text": "<@U0DEVILFAN> doesn't have any :buns: yet."
+                })
+            ),
+        ]
+    );
+}
+
+#[tokio::test]
+async fn unknown_slash_commands_are_answered_straight_away() {
+    let harness = Harness::start().await.unwrap();
+
+    let response = harness
+        .send(slash_command(&harness, "/devilbot", "dance"))
+        .await
+        .unwrap();
+
+    let body: Json = serde_json::from_slice(response.body()).unwrap();
+    assert_eq!(
+        body,
+        json!({"response_type": "ephemeral", "text": "I don't know the command `dance`."})
+    );
+    assert!(harness.slack.calls().is_empty());
+}
+
+#[tokio::test]
+async fn redelivered_events_run_once() {
+    let harness = Harness::start().await.unwrap();
+
+    harness.send(PING).await.unwrap();
+    let retry = harness.send(PING).await.unwrap();
+
+    assert_eq!(retry.status(), StatusCode::OK);
+    assert_eq!(retry.headers()["x-slack-no-retry"], "1");
+    assert_eq!(harness.slack.calls().len(), 1);
+}
+
+#[tokio::test]
+async fn unsigned_requests_are_rejected() {
+    let harness = Harness::start().await.unwrap();
+    let request = lambda_http::http::Request::builder()
+        .method("POST")
+        .uri("/")
+        .body(lambda_http::Body::from(PING))
+        .unwrap();
+
+    let response = devil_bot_rust::handler(request, &harness.state)
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    assert!(harness.slack.calls().is_empty());
+}
+
+#[tokio::test]
+async fn malformed_bodies_are_rejected() {
+    let harness = Harness::start().await.unwrap();
+
+    let response = harness.send(r#"{"type": "event_callback"}"#).await.unwrap();
+
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert!(harness.slack.calls().is_empty());
+}
+
+#[tokio::test]
+async fn url_verification_echoes_the_challenge() {
+    let harness = Harness::start().await.unwrap();
+
+    let response = harness.send(URL_VERIFICATION).await.unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+    let body: Json = serde_json::from_slice(response.body()).unwrap();
+    assert_eq!(
+        body,
+        json!({"challenge": "3eZbrw1aBm2rZgRNFdxV2595E9CY3gmdALWMmHkvFXO7tYXAYM8P"})
+    );
+}
+
+#[tokio::test]
+async fn app_rate_limited_is_acknowledged() {
+    let harness = Harness::start().await.unwrap();
+
+    let response = harness
+        .send(
+            json!({
+                "token": "Jhj5dZrVaK7ZwHHjRyZWjbDl",
+                "type": "app_rate_limited",
+                "team_id": "T0DEVILS",
+                "minute_rate_limited": 1518467820,
+                "api_app_id": "A0DEVILBOT"
+            })
+            .to_string(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+    assert!(harness.slack.calls().is_empty());
+    assert!(harness.store.writes().is_empty());
+}